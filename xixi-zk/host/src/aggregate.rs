@@ -0,0 +1,238 @@
+//! Leaderboards: many player proofs composed into one receipt
+//!
+//! The aggregation guest verifies each player journal with `env::verify`;
+//! the player receipts are added as assumptions and resolved by the prover,
+//! so the final receipt stands on its own.
+
+use methods::{XIXI_AGGREGATOR_ELF, XIXI_AGGREGATOR_ID, XIXI_VERIFIER_ID};
+use risc0_zkvm::sha::Digest;
+use serde_json::{json, Value};
+use xixi_core::Leaderboard;
+use xixi_verify::registry::Registry;
+
+use crate::prover::GuestInput;
+use crate::{prover, Error, Policy, ProofEnvelope, ProveOptions, Reason, Result};
+
+/// Prove a leaderboard over `proofs`
+///
+/// All proofs must be succinct, for the same game and challenge and from
+/// the same guest image, and ship the credential they commit (signed, for a
+/// key credential). A player with several proofs is ranked by the best one.
+///
+/// Anonymous proofs are refused, revealed or not: the guest only sees the
+/// credential hash, and with a fresh salt per proof one player could take
+/// several places.
+pub fn aggregate(proofs: &[ProofEnvelope], opts: &ProveOptions) -> Result<ProofEnvelope> {
+    if proofs.is_empty() {
+        return Err(Error::Aggregate("no proofs given".into()));
+    }
+    if opts.image.is_some() {
+        return Err(Error::Aggregate("--image is not supported".into()));
+    }
+
+    // Cheap checks of every proof first, then the receipts
+    let image_id = proofs[0].image_id.unwrap_or(XIXI_VERIFIER_ID.into());
+    let game = proofs[0].game()?;
+    for (i, proof) in proofs.iter().enumerate() {
+        let fail = |message: String| Error::Aggregate(format!("proof {}: {}", i, message));
+        if proof.image_id.unwrap_or(image_id) != image_id {
+            return Err(fail(format!("proven with image {}, others with {}", proof.image_id.unwrap(), image_id)));
+        }
+        if proof.is_chunk() {
+            return Err(fail("is a route chunk, join the chunks first".into()));
+        }
+        if proof.game()? != game {
+            return Err(fail("is for another game".into()));
+        }
+        let output = proof.output()?;
+        if output.challenge != proofs[0].output()?.challenge {
+            return Err(fail("is for another challenge".into()));
+        }
+        if output.category != proofs[0].output()?.category {
+            return Err(fail("is for another category".into()));
+        }
+        if proof.user_cred.is_none() || !proof.cred_salt.is_empty() {
+            return Err(fail("is anonymous, its salted credential hash cannot be ranked once per player".into()));
+        }
+        if output.score_threshold.is_some() {
+            return Err(fail("only shows a score threshold, there is no score to rank".into()));
+        }
+        // The entry is ranked under the committed hash: the credential must
+        // be the one committed, and a key credential must have signed
+        Policy::default().check_shipped_credential(proof).map_err(|(_, message)| fail(message))?;
+    }
+    let mut assumptions = Vec::with_capacity(proofs.len());
+    let mut journals = Vec::with_capacity(proofs.len());
+    for (i, proof) in proofs.iter().enumerate() {
+        let fail = |message: String| Error::Aggregate(format!("proof {}: {}", i, message));
+        if !prover::is_resolvable(&proof.receipt) {
+            return Err(fail("not a succinct receipt, prove it with --receipt succinct".into()));
+        }
+        proof.receipt.verify(image_id).map_err(|e| fail(format!("does not verify: {}", e)))?;
+
+        journals.push(proof.receipt.journal.bytes.clone());
+        assumptions.push(proof.receipt.clone());
+    }
+
+    let mut input = GuestInput::default();
+    input.write(&<[u8; 32]>::from(image_id))?.write(&journals)?;
+    if opts.progress {
+        eprintln!("[aggregate] proving leaderboard of {} proof(s)...", proofs.len());
+    }
+    let receipt = prover::prove_composed(input, assumptions, XIXI_AGGREGATOR_ELF, opts).map_err(Error::Prove)?;
+
+    let mut proof = ProofEnvelope::new(XIXI_AGGREGATOR_ID, receipt, Some(Vec::new()));
+    proof.compressed = opts.compress;
+    Ok(proof)
+}
+
+/// Verify a leaderboard proof, bound to `challenge` if given
+///
+/// The entries must come from the compiled-in guest or a registered image.
+pub fn verify_leaderboard(proof: &ProofEnvelope, challenge: Option<&str>) -> Result<Leaderboard> {
+    let reject = |reason, message: String| Error::Rejected { reason, message };
+    proof
+        .receipt
+        .verify(XIXI_AGGREGATOR_ID)
+        .map_err(|e| reject(Reason::InvalidProof, format!("Receipt verification failed: {}", e)))?;
+    let leaderboard = proof
+        .leaderboard()
+        .map_err(|e| reject(Reason::InvalidProof, format!("Bad journal: {:#}", e)))?;
+
+    let image_id = Digest::from(leaderboard.image_id);
+    let registry = Registry::load().map_err(Error::Registry)?;
+    if image_id != Digest::from(XIXI_VERIFIER_ID) && registry.find_id(&image_id).is_none() {
+        return Err(reject(Reason::ImageNotAllowed, format!("Entries proven with unknown guest image {}", image_id)));
+    }
+    if challenge.is_some() && leaderboard.challenge.as_deref() != challenge {
+        return Err(reject(Reason::ChallengeMismatch, "Leaderboard is not bound to the challenge".into()));
+    }
+    Ok(leaderboard)
+}
+
+/// Leaderboard as printed by `host leaderboard` and `host inspect`
+pub fn leaderboard_json(leaderboard: &Leaderboard) -> Value {
+    // Entries are one per credential, so a repeated nullifier means the
+    // same route under different credentials
+    let shared = |nullifier: &Option<[u8; 32]>| {
+        nullifier.is_some() && leaderboard.entries.iter().filter(|e| e.route_nullifier == *nullifier).count() > 1
+    };
+    let entries: Vec<_> = leaderboard
+        .entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            json!({
+                "rank": i + 1,
+                "user_cred_hash": hex::encode(entry.user_cred_hash),
+                "scores": entry.scores,
+                "route_nullifier": entry.route_nullifier.map(hex::encode),
+                "shared_route": shared(&entry.route_nullifier),
+            })
+        })
+        .collect();
+    json!({
+        "game": hex::encode(leaderboard.config_hash),
+        "image_id": Digest::from(leaderboard.image_id).to_string(),
+        "challenge": leaderboard.challenge,
+        "category": leaderboard.category,
+        "entries": entries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use risc0_zkvm::{FakeReceipt, InnerReceipt, Receipt, ReceiptClaim};
+    use xixi_core::{LeaderboardEntry, Output, OUTPUT_VERSION};
+    use xixi_verify::reveal::credential_commitment;
+
+    use super::*;
+
+    /// Unproven player proof with a current journal
+    fn player(cred: &str, edit: impl FnOnce(&mut Output)) -> ProofEnvelope {
+        let mut output = Output {
+            version: OUTPUT_VERSION,
+            config_hash: [7; 32],
+            user_cred_hash: credential_commitment(cred.as_bytes(), &[]),
+            scores: vec![100],
+            chunk_image_id: None,
+            challenge: None,
+            route_commitment: None,
+            route_nullifier: None,
+            route_ciphertext: None,
+            score_threshold: None,
+            disclosed: Vec::new(),
+            category: None,
+            route_steps: Vec::new(),
+            game_id: None,
+        };
+        edit(&mut output);
+        let words = risc0_zkvm::serde::to_vec(&output).unwrap();
+        let journal: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+        let claim = ReceiptClaim::ok(Digest::ZERO, journal.clone());
+        let receipt = Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), journal);
+        ProofEnvelope::new(Digest::ZERO, receipt, Some(cred.as_bytes().to_vec()))
+    }
+
+    fn refusal(proofs: &[ProofEnvelope]) -> String {
+        match aggregate(proofs, &ProveOptions::default()) {
+            Err(Error::Aggregate(message)) => message,
+            Err(e) => panic!("unexpected error {}", e),
+            Ok(_) => panic!("aggregated"),
+        }
+    }
+
+    #[test]
+    fn refuses_mixed_proofs() {
+        let ok = || player("alice", |_| {});
+        assert!(refusal(&[]).contains("no proofs"));
+        assert!(refusal(&[ok(), player("bob", |o| o.config_hash = [8; 32])]).contains("another game"));
+        assert!(refusal(&[ok(), player("bob", |o| o.challenge = Some("cup".into()))]).contains("another challenge"));
+        assert!(refusal(&[ok(), player("bob", |o| o.category = xixi_core::Category::builtin("100%"))]).contains("another category"));
+        let hidden = player("bob", |o| o.score_threshold = Some(xixi_core::ScoreThreshold::new(50, &o.scores)));
+        assert!(refusal(&[ok(), hidden]).starts_with("proof 1: only shows a score threshold"));
+
+        let mut other_image = player("bob", |_| {});
+        other_image.image_id = Some(Digest::from([1u32; 8]));
+        assert!(refusal(&[ok(), other_image]).contains("proven with image"));
+
+        let mut anonymous = player("bob", |_| {});
+        anonymous.user_cred = None;
+        assert!(refusal(&[ok(), anonymous]).contains("anonymous"));
+        let mut salted = player("bob", |_| {});
+        salted.cred_salt = vec![1; 16];
+        assert!(refusal(&[salted]).contains("anonymous"));
+
+        // Ranked under someone else's credential hash
+        let mut swapped = player("bob", |_| {});
+        swapped.user_cred = Some(b"mallory".to_vec());
+        assert!(refusal(&[ok(), swapped]).starts_with("proof 1: User credential hash mismatch"));
+        // A key credential that did not sign
+        let key = crate::SigningKey::from_secret("ed25519", &[1; 32]).unwrap();
+        let unsigned = player(&key.identity().to_credential(), |_| {});
+        assert!(refusal(&[ok(), unsigned]).contains("not signed"));
+    }
+
+    #[test]
+    fn json_flags_shared_routes() {
+        let entry = |cred: u8, score: i64, route: Option<u8>| LeaderboardEntry {
+            user_cred_hash: [cred; 32],
+            scores: vec![score],
+            route_nullifier: route.map(|r| [r; 32]),
+        };
+        let leaderboard = Leaderboard {
+            config_hash: [7; 32],
+            image_id: [0; 32],
+            challenge: None,
+            category: None,
+            entries: vec![entry(1, 30, Some(5)), entry(2, 20, Some(5)), entry(3, 10, Some(6)), entry(4, 5, None), entry(5, 1, None)],
+        };
+        let json = leaderboard_json(&leaderboard);
+        let entries = json["entries"].as_array().unwrap();
+        let ranks: Vec<_> = entries.iter().map(|e| e["rank"].as_u64().unwrap()).collect();
+        let shared: Vec<_> = entries.iter().map(|e| e["shared_route"].as_bool().unwrap()).collect();
+        assert_eq!(ranks, vec![1, 2, 3, 4, 5]);
+        assert_eq!(shared, vec![true, true, false, false, false]);
+        assert_eq!(json["game"], hex::encode([7; 32]));
+    }
+}
@@ -0,0 +1,167 @@
+//! Cycle profiling of a route corpus against a stored baseline
+//!
+//! Routes are only executed, never proven, so a corpus run takes seconds.
+//! The guest reports per-phase cycle counts on `PROFILE_FD`; the totals come
+//! from the executor session.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use methods::XIXI_VERIFIER_ELF;
+use risc0_zkvm::default_executor;
+use serde::{Deserialize, Serialize};
+use xixi_core::{CycleProfile, OutputOptions, PROFILE_CHUNKS, PROFILE_FD, PROFILE_PHASES};
+
+use xixi_zk::{guest_env_builder, route_to_bytes, ChainInput};
+
+/// Default allowed growth of any measured number, in percent
+const DEFAULT_TOLERANCE_PCT: f64 = 1.0;
+
+/// Measurements of one route
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RouteBench {
+    /// User cycles of the whole session, as reported by the executor
+    user_cycles: u64,
+    segments: usize,
+    profile: CycleProfile,
+}
+
+/// Stored baseline, keyed by route path as given on the command line
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Baseline {
+    routes: BTreeMap<String, RouteBench>,
+}
+
+/// Expand directories into their files, sorted for stable output
+fn collect_routes(args: &[String]) -> Result<Vec<PathBuf>> {
+    let mut routes = Vec::new();
+    for arg in args {
+        let path = Path::new(arg);
+        if path.is_dir() {
+            let mut entries = fs::read_dir(path)?
+                .map(|e| e.map(|e| e.path()))
+                .collect::<Result<Vec<_>, _>>()?;
+            entries.retain(|p| p.is_file());
+            entries.sort();
+            routes.extend(entries);
+        } else {
+            routes.push(path.to_path_buf());
+        }
+    }
+    Ok(routes)
+}
+
+fn bench_route(config_bytes: &[u8], route: &Path, chunks: bool) -> Result<RouteBench> {
+    let route_bytes = route_to_bytes(&fs::read(route).with_context(|| format!("Failed to read {}", route.display()))?)?;
+    let mode = if chunks { PROFILE_CHUNKS } else { PROFILE_PHASES };
+
+    let mut profile_bytes = Vec::new();
+    let session = {
+        let env = guest_env_builder(config_bytes, &[0u8; 32], &route_bytes, mode, &ChainInput::Off, &OutputOptions::default())?
+            .write_fd(PROFILE_FD, &mut profile_bytes)
+            .build()?;
+        default_executor().execute(env, XIXI_VERIFIER_ELF)?
+    };
+    let profile: CycleProfile = risc0_zkvm::serde::from_slice(&profile_bytes)
+        .context("Guest did not report a cycle profile")?;
+
+    Ok(RouteBench {
+        user_cycles: session.cycles(),
+        segments: session.segments.len(),
+        profile,
+    })
+}
+
+/// Named numbers compared against the baseline
+fn metrics(bench: &RouteBench) -> [(&'static str, u64); 6] {
+    let p = &bench.profile;
+    [
+        ("user_cycles", bench.user_cycles),
+        ("read_input", p.read_input),
+        ("hashing", p.hashing),
+        ("parse_route", p.parse_route),
+        ("simulate_game", p.simulate_game),
+        ("final_checks", p.final_checks),
+    ]
+}
+
+/// Returns the regressions of `current` over `base`, beyond `tolerance_pct`
+fn compare(base: &RouteBench, current: &RouteBench, tolerance_pct: f64) -> Vec<String> {
+    metrics(base)
+        .iter()
+        .zip(metrics(current).iter())
+        .filter(|((_, old), (_, new))| *new as f64 > *old as f64 * (1.0 + tolerance_pct / 100.0))
+        .map(|((name, old), (_, new))| {
+            let pct = (*new as f64 - *old as f64) * 100.0 / (*old).max(1) as f64;
+            format!("{}: {} -> {} (+{:.2}%)", name, old, new, pct)
+        })
+        .collect()
+}
+
+/// Entry of `host bench`
+pub fn run(config_path: &str, baseline_path: &str, rest: &[String]) -> Result<()> {
+    let mut update = false;
+    let mut chunks = false;
+    let mut tolerance_pct = DEFAULT_TOLERANCE_PCT;
+    let mut route_args = Vec::new();
+    let mut iter = rest.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--update" => update = true,
+            "--chunks" => chunks = true,
+            "--tolerance" => {
+                let value = iter.next().context("--tolerance requires a value")?;
+                tolerance_pct = value.parse().context("Invalid tolerance")?;
+            }
+            _ => route_args.push(arg.clone()),
+        }
+    }
+
+    let config_bytes = fs::read(config_path).context("Failed to read config")?;
+    let routes = collect_routes(&route_args)?;
+    if routes.is_empty() {
+        bail!("No route files given");
+    }
+
+    let baseline: Option<Baseline> = match fs::read(baseline_path) {
+        Ok(bytes) => Some(serde_json::from_slice(&bytes).context("Invalid baseline file")?),
+        Err(_) if update => None,
+        Err(e) => bail!("Failed to read baseline {} ({}); use --update to create it", baseline_path, e),
+    };
+
+    let mut results = Baseline::default();
+    let mut regressions = 0;
+    for route in &routes {
+        let key = route.to_string_lossy().into_owned();
+        let bench = bench_route(&config_bytes, route, chunks)
+            .with_context(|| format!("Failed to run route {}", key))?;
+
+        println!("{}", key);
+        for (name, value) in metrics(&bench) {
+            println!("  {:<14} {:>10}", name, value);
+        }
+        if chunks {
+            println!("  route_chunks   {:?}", bench.profile.route_chunks);
+        }
+
+        if let Some(base) = baseline.as_ref().and_then(|b| b.routes.get(&key)) {
+            for regression in compare(base, &bench, tolerance_pct) {
+                println!("  REGRESSION {}", regression);
+                regressions += 1;
+            }
+        } else if baseline.is_some() {
+            println!("  (not in baseline)");
+        }
+        results.routes.insert(key, bench);
+    }
+
+    if update {
+        fs::write(baseline_path, serde_json::to_string_pretty(&results)?)?;
+        println!("Baseline written to: {}", baseline_path);
+    } else if regressions > 0 {
+        bail!("{} cycle regression(s) beyond {}%", regressions, tolerance_pct);
+    }
+    Ok(())
+}
@@ -0,0 +1,199 @@
+//! Chained proofs: a long route proven in chunks, then joined
+//!
+//! Each chunk proof starts from the state the previous one ended in, so a
+//! route can be proven while it is played, and a changed chunk only needs
+//! itself and the chunks after it proven again. The join composes the chunk
+//! receipts into a proof with the same `Output` as proving the whole route.
+
+use methods::{XIXI_VERIFIER_ELF, XIXI_VERIFIER_ID};
+use risc0_zkvm::sha::Digest as ImageId;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use xixi_core::{ChainInput, ChainStart, ChainState, ChunkClaim, OutputOptions, GENESIS_STATE, NULLIFIER_DOMAIN, PROFILE_OFF};
+
+use crate::{check_challenge, check_size, guest_env_builder, guest_input, prover, Error, ProofEnvelope, ProveOptions, Result};
+use crate::{MAX_CONFIG_SIZE, MAX_ROUTE_SIZE, MAX_USER_CRED_SIZE};
+
+/// Commitment of `state` as the guest computes it
+pub fn state_commitment(state: &ChainState, config_hash: &[u8; 32]) -> [u8; 32] {
+    Sha256::digest(state.commitment_bytes(config_hash)).into()
+}
+
+/// Route nullifier before the first node, as the guest computes it
+pub(crate) fn nullifier_seed(config_hash: &[u8; 32]) -> [u8; 32] {
+    Sha256::new().chain_update(NULLIFIER_DOMAIN).chain_update(config_hash).finalize().into()
+}
+
+/// Route nullifier `hash` carried on over the nodes of `route_bytes`
+pub(crate) fn extend_nullifier(mut hash: [u8; 32], route_bytes: &[u8]) -> [u8; 32] {
+    for node in route_bytes.chunks(4) {
+        hash = Sha256::new().chain_update(hash).chain_update(node).finalize().into();
+    }
+    hash
+}
+
+/// Whether `opts` asks for output only a proof of the whole route can give
+fn whole_route_only(opts: &ProveOptions) -> bool {
+    opts.route_salt.is_some()
+        || opts.judge_key.is_some()
+        || !opts.disclose.is_empty()
+        || opts.category.is_some()
+        || opts.route_steps.is_some()
+}
+
+/// Prove the route chunk `route_bytes` for `user_cred`, continuing from the
+/// chunk proof `previous` (from the start of the game when `None`)
+///
+/// Only chunks proven for the same credential join.
+pub fn prove_chunk(
+    config_bytes: &[u8],
+    user_cred: &[u8],
+    previous: Option<&ProofEnvelope>,
+    route_bytes: &[u8],
+    opts: &ProveOptions,
+) -> Result<ProofEnvelope> {
+    check_size("config", config_bytes, MAX_CONFIG_SIZE)?;
+    check_size("user credential", user_cred, MAX_USER_CRED_SIZE)?;
+    check_size("route", route_bytes, MAX_ROUTE_SIZE)?;
+    check_challenge(opts)?;
+    if opts.image.is_some() {
+        return Err(Error::Chain("--image is not supported for chunks".into()));
+    }
+    if whole_route_only(opts) {
+        return Err(Error::Chain(
+            "--route-salt, --judge, --disclose, --category and public steps need the whole route, prove it without chunks"
+                .into(),
+        ));
+    }
+
+    let config_hash: [u8; 32] = Sha256::digest(config_bytes).into();
+    let user_cred_hash: [u8; 32] = Sha256::digest(user_cred).into();
+    let start = match previous {
+        Some(previous) => {
+            let claim = previous.chunk_claim()?;
+            if claim.config_hash != config_hash {
+                return Err(Error::Chain("previous chunk is for another game".into()));
+            }
+            if claim.user_cred_hash != user_cred_hash {
+                return Err(Error::Chain("previous chunk is for another credential".into()));
+            }
+            if claim.challenge != opts.challenge {
+                return Err(Error::Chain("previous chunk is for another challenge".into()));
+            }
+            Some(ChainStart { state: claim.state_out, route: claim.route_out })
+        }
+        None => None,
+    };
+    let state_in = start.as_ref().map_or(GENESIS_STATE, |start| state_commitment(&start.state, &config_hash));
+    let route_in = start.as_ref().map_or([0; 32], |start| start.route);
+
+    let options = OutputOptions { challenge: opts.challenge.clone(), ..Default::default() };
+    let challenge_hash: [u8; 32] = opts.challenge.as_ref().map_or([0; 32], |c| Sha256::digest(c).into());
+    let builder = guest_env_builder(config_bytes, &user_cred_hash, route_bytes, PROFILE_OFF, &ChainInput::Step(start), &options)?;
+    let input_digest = Sha256::new()
+        .chain_update(config_hash)
+        .chain_update(user_cred_hash)
+        .chain_update(challenge_hash)
+        .chain_update(state_in)
+        .chain_update(route_in)
+        .chain_update(route_bytes)
+        .finalize();
+    let default_work_dir = std::env::temp_dir().join(format!("xixi-zk-{}.work", hex::encode(&input_digest[..8])));
+    let receipt = prover::prove(builder, XIXI_VERIFIER_ELF, opts, default_work_dir, &input_digest)
+        .map_err(Error::Prove)?;
+
+    let mut proof = ProofEnvelope::new(XIXI_VERIFIER_ID, receipt, Some(Vec::new()));
+    proof.compressed = opts.compress;
+    Ok(proof)
+}
+
+/// Join chunk proofs, in route order, into a proof for `user_cred`
+///
+/// Chunks must be succinct, proven with the compiled-in guest and for the
+/// same challenge, which the joined proof commits; `--challenge` on the join
+/// only checks it.
+pub fn join_chunks(chunks: &[ProofEnvelope], user_cred: &[u8], opts: &ProveOptions) -> Result<ProofEnvelope> {
+    check_size("user credential", user_cred, MAX_USER_CRED_SIZE)?;
+    check_challenge(opts)?;
+    if opts.image.is_some() {
+        return Err(Error::Chain("--image is not supported for chunks".into()));
+    }
+    if whole_route_only(opts) {
+        return Err(Error::Chain(
+            "--route-salt, --judge, --disclose, --category and public steps need the whole route, prove it without chunks"
+                .into(),
+        ));
+    }
+
+    // Same checks as the guest, for errors before anything is proven
+    let user_cred_hash: [u8; 32] = Sha256::digest(user_cred).into();
+    let mut journals = Vec::with_capacity(chunks.len());
+    let mut expected = GENESIS_STATE;
+    let mut route = None;
+    let mut challenge = None;
+    for (i, chunk) in chunks.iter().enumerate() {
+        let fail = |message: String| Error::Chain(format!("chunk {}: {}", i, message));
+        let claim = chunk.chunk_claim().map_err(|e| fail(format!("{:#}", e)))?;
+        let route_in = route.unwrap_or_else(|| nullifier_seed(&claim.config_hash));
+        if claim.state_in != expected || claim.route_in != route_in {
+            return Err(fail("does not continue from the chunk before it".into()));
+        }
+        if claim.user_cred_hash != user_cred_hash {
+            return Err(fail("proven for another credential".into()));
+        }
+        if opts.challenge.is_some() && claim.challenge != opts.challenge {
+            return Err(fail("proven for another challenge".into()));
+        }
+        if challenge.get_or_insert_with(|| claim.challenge.clone()) != &claim.challenge {
+            return Err(fail("proven for another challenge than the chunk before it".into()));
+        }
+        if !prover::is_resolvable(&chunk.receipt) {
+            return Err(fail("not a succinct receipt, prove it with --receipt succinct".into()));
+        }
+        chunk
+            .receipt
+            .verify(XIXI_VERIFIER_ID)
+            .map_err(|e| fail(format!("does not verify with the current guest: {}", e)))?;
+        expected = state_commitment(&claim.state_out, &claim.config_hash);
+        route = Some(claim.route_out);
+
+        journals.push(chunk.receipt.journal.bytes.clone());
+    }
+    let last = chunks.last().ok_or_else(|| Error::Chain("no chunks given".into()))?.chunk_claim()?;
+    if last.last_node != 1 {
+        return Err(Error::Chain("last chunk does not end with node 1".into()));
+    }
+
+    let image_id = <[u8; 32]>::from(ImageId::from(XIXI_VERIFIER_ID));
+    let join = ChainInput::Join { image_id, journals };
+    let options = OutputOptions {
+        challenge: opts.challenge.clone(),
+        score_threshold: opts.score_threshold,
+        ..Default::default()
+    };
+    let input = guest_input(&[], &user_cred_hash, &[], PROFILE_OFF, &join, &options)?;
+    let assumptions = chunks.iter().map(|chunk| chunk.receipt.clone()).collect();
+    if opts.progress {
+        eprintln!("[join] proving {} chunk(s)...", chunks.len());
+    }
+    let receipt = prover::prove_composed(input, assumptions, XIXI_VERIFIER_ELF, opts).map_err(Error::Prove)?;
+
+    let mut proof = ProofEnvelope::new(XIXI_VERIFIER_ID, receipt, Some(user_cred.to_vec()));
+    proof.compressed = opts.compress;
+    Ok(proof)
+}
+
+/// Chunk claim as printed by `host prove-chunk` and `host inspect`
+pub fn chunk_json(claim: &ChunkClaim) -> Value {
+    let p = &claim.state_out.player;
+    json!({
+        "config_hash": hex::encode(claim.config_hash),
+        "game_id": hex::encode(claim.game_id),
+        "user_cred_hash": hex::encode(claim.user_cred_hash),
+        "challenge": claim.challenge,
+        "state_in": hex::encode(claim.state_in),
+        "state_out": hex::encode(state_commitment(&claim.state_out, &claim.config_hash)),
+        "last_node": claim.last_node,
+        "player": {"hp": p.hp, "atk": p.atk, "def": p.def, "mdef": p.mdef, "exp": p.exp, "lv": p.lv},
+    })
+}
@@ -0,0 +1,289 @@
+//! On-disk checkpoints that let an interrupted `prove` resume
+//!
+//! Layout of a work directory:
+//! - `.xixi-zk-work`: marker with the job key, written when the dir is
+//!   created; a non-empty directory without it is never used
+//! - `manifest.json`: job key, journal and segment file digests, written
+//!   once execution has finished
+//! - `segments/`: executor segments as written by r0vm
+//! - `<name>.ckpt`: one receipt per finished step, prefixed with the SHA-256
+//!   of its payload
+//!
+//! Every file is written to a temporary name and renamed into place, and
+//! every checkpoint is checked against its digest before reuse, so a file cut
+//! short by a crash is proven again instead of being trusted. Only these
+//! files are ever deleted, so files of the user's in the directory survive.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// What execution produced, enough to skip it on resume
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Digest of everything that influences the proof (inputs, image, options)
+    pub job_key: String,
+    pub journal: Vec<u8>,
+    pub user_cycles: u64,
+    /// Segment files relative to the work dir, with their SHA-256
+    pub segments: Vec<(PathBuf, String)>,
+}
+
+pub struct WorkDir {
+    root: PathBuf,
+}
+
+fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, bytes)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+const MARKER: &str = ".xixi-zk-work";
+
+impl WorkDir {
+    /// Open or create `root` for the job identified by `job_key`
+    ///
+    /// A work dir left by a different job, or a non-empty directory that is
+    /// no work dir at all (`--work-dir .` by mistake), is refused rather than
+    /// wiped.
+    pub fn open(root: &Path, job_key: &str) -> Result<Self> {
+        let work = Self { root: root.to_path_buf() };
+        let marker = root.join(MARKER);
+        match fs::read_to_string(&marker) {
+            Ok(key) if key == job_key => {}
+            Ok(_) => bail!(
+                "Work dir {} belongs to another prove job; remove it or pick another --work-dir",
+                root.display()
+            ),
+            Err(_) => {
+                let empty = fs::read_dir(root).map_or(true, |mut entries| entries.next().is_none());
+                if !empty {
+                    bail!("{} is not empty and not a work dir; pick another --work-dir", root.display());
+                }
+                fs::create_dir_all(root)?;
+                write_atomic(&marker, job_key.as_bytes())?;
+            }
+        }
+        fs::create_dir_all(work.segment_dir())?;
+        Ok(work)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn segment_dir(&self) -> PathBuf {
+        self.root.join("segments")
+    }
+
+    fn manifest_path(&self) -> PathBuf {
+        self.root.join("manifest.json")
+    }
+
+    pub fn manifest(&self) -> Result<Option<Manifest>> {
+        match fs::read(self.manifest_path()) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes).context("Corrupt work dir manifest")?)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Manifest of a finished execution whose segment files are all intact
+    pub fn valid_manifest(&self) -> Result<Option<Manifest>> {
+        let Some(manifest) = self.manifest()? else {
+            return Ok(None);
+        };
+        for (path, digest) in &manifest.segments {
+            let intact = fs::read(self.root.join(path))
+                .map(|bytes| hex::encode(Sha256::digest(&bytes)) == *digest)
+                .unwrap_or(false);
+            if !intact {
+                eprintln!("[prove] segment {} damaged, executing again", path.display());
+                return Ok(None);
+            }
+        }
+        Ok(Some(manifest))
+    }
+
+    /// Record a finished execution; `segments` are absolute paths inside the segment dir
+    pub fn store_manifest(
+        &self,
+        job_key: &str,
+        journal: Vec<u8>,
+        user_cycles: u64,
+        segments: &[PathBuf],
+    ) -> Result<Manifest> {
+        let segments = segments
+            .iter()
+            .map(|path| {
+                let digest = hex::encode(Sha256::digest(fs::read(path)?));
+                let relative = path.strip_prefix(&self.root).unwrap_or(path).to_path_buf();
+                Ok((relative, digest))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let manifest = Manifest { job_key: job_key.to_string(), journal, user_cycles, segments };
+        write_atomic(&self.manifest_path(), &serde_json::to_vec_pretty(&manifest)?)?;
+        Ok(manifest)
+    }
+
+    /// Drop a stale execution before running it again
+    pub fn reset(&self) -> Result<()> {
+        self.remove_contents()?;
+        fs::create_dir_all(self.segment_dir())?;
+        Ok(())
+    }
+
+    /// Delete the manifest, checkpoints and segments, nothing else
+    fn remove_contents(&self) -> Result<()> {
+        let _ = fs::remove_file(self.manifest_path());
+        for entry in fs::read_dir(&self.root)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == "ckpt" || e == "tmp") {
+                fs::remove_file(path)?;
+            }
+        }
+        let _ = fs::remove_dir_all(self.segment_dir());
+        Ok(())
+    }
+
+    fn checkpoint_path(&self, name: &str) -> PathBuf {
+        self.root.join(format!("{}.ckpt", name))
+    }
+
+    /// Load checkpoint `name`, or `None` if missing or damaged
+    pub fn load<T: DeserializeOwned>(&self, name: &str) -> Option<T> {
+        let bytes = fs::read(self.checkpoint_path(name)).ok()?;
+        if bytes.len() < 32 || Sha256::digest(&bytes[32..]).as_slice() != &bytes[..32] {
+            eprintln!("[prove] checkpoint {} damaged, proving it again", name);
+            return None;
+        }
+        bincode::deserialize(&bytes[32..]).ok()
+    }
+
+    pub fn store<T: Serialize>(&self, name: &str, value: &T) -> Result<()> {
+        let payload = bincode::serialize(value)?;
+        let mut bytes = Sha256::digest(&payload).to_vec();
+        bytes.extend_from_slice(&payload);
+        write_atomic(&self.checkpoint_path(name), &bytes)
+    }
+
+    /// Reuse checkpoint `name` or compute and store it
+    pub fn get_or<T, F>(&self, name: &str, compute: F) -> Result<(T, bool)>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Result<T>,
+    {
+        if let Some(value) = self.load(name) {
+            return Ok((value, true));
+        }
+        let value = compute()?;
+        self.store(name, &value)?;
+        Ok((value, false))
+    }
+
+    /// Remove the work dir once the proof is written; the directory itself
+    /// stays if something else was put in it
+    pub fn remove(self) -> Result<()> {
+        self.remove_contents()?;
+        fs::remove_file(self.root.join(MARKER))?;
+        let _ = fs::remove_dir(&self.root);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fresh path under the temp dir, not created
+    fn scratch(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("xixi-zk-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        path
+    }
+
+    #[test]
+    fn refuses_populated_dir() {
+        let root = scratch("populated");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("notes.txt"), "mine").unwrap();
+        fs::write(root.join("old.ckpt"), "mine too").unwrap();
+
+        assert!(WorkDir::open(&root, "job").is_err());
+        assert_eq!(fs::read_to_string(root.join("notes.txt")).unwrap(), "mine");
+        assert!(root.join("old.ckpt").exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn remove_keeps_foreign_files() {
+        let root = scratch("foreign");
+        let work = WorkDir::open(&root, "job").unwrap();
+        work.store("step", &1u32).unwrap();
+        fs::write(root.join("notes.txt"), "mine").unwrap();
+
+        work.remove().unwrap();
+        assert!(!root.join("step.ckpt").exists() && !root.join(MARKER).exists());
+        assert_eq!(fs::read_to_string(root.join("notes.txt")).unwrap(), "mine");
+        fs::remove_dir_all(&root).unwrap();
+
+        // Nothing else in it: the directory goes too
+        WorkDir::open(&root, "job").unwrap().remove().unwrap();
+        assert!(!root.exists());
+    }
+
+    #[test]
+    fn refuses_other_job() {
+        let root = scratch("other-job");
+        WorkDir::open(&root, "job").unwrap();
+        assert!(WorkDir::open(&root, "job").is_ok());
+        assert!(WorkDir::open(&root, "another job").is_err());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn damaged_checkpoint_is_recomputed() {
+        let root = scratch("damaged");
+        let work = WorkDir::open(&root, "job").unwrap();
+        work.store("join-0", &vec![1u32, 2, 3]).unwrap();
+        assert_eq!(work.get_or("join-0", || Ok(vec![0u32])).unwrap(), (vec![1, 2, 3], true));
+
+        // Flip a payload byte, then cut the file short
+        let path = root.join("join-0.ckpt");
+        let mut bytes = fs::read(&path).unwrap();
+        *bytes.last_mut().unwrap() ^= 1;
+        fs::write(&path, &bytes).unwrap();
+        assert_eq!(work.load::<Vec<u32>>("join-0"), None);
+        fs::write(&path, &bytes[..20]).unwrap();
+        assert_eq!(work.get_or("join-0", || Ok(vec![4u32])).unwrap(), (vec![4], false));
+        assert_eq!(work.load::<Vec<u32>>("join-0"), Some(vec![4]));
+        work.remove().unwrap();
+    }
+
+    #[test]
+    fn damaged_segment_voids_manifest() {
+        let root = scratch("manifest");
+        let work = WorkDir::open(&root, "job").unwrap();
+        let segment = work.segment_dir().join("0");
+        fs::write(&segment, b"segment").unwrap();
+        work.store_manifest("job", vec![9], 100, std::slice::from_ref(&segment)).unwrap();
+        let manifest = work.valid_manifest().unwrap().unwrap();
+        assert_eq!((manifest.journal, manifest.user_cycles), (vec![9], 100));
+        assert_eq!(manifest.segments[0].0, Path::new("segments/0"));
+
+        fs::write(&segment, b"segmenT").unwrap();
+        assert!(work.valid_manifest().unwrap().is_none());
+        fs::remove_file(&segment).unwrap();
+        assert!(work.valid_manifest().unwrap().is_none());
+
+        fs::write(root.join("manifest.json"), b"{").unwrap();
+        assert!(work.manifest().is_err());
+        work.reset().unwrap();
+        assert!(work.manifest().unwrap().is_none() && work.segment_dir().exists());
+        work.remove().unwrap();
+    }
+}
@@ -0,0 +1,336 @@
+//! Background proving queue over a spool directory
+//!
+//! Each job is a subdirectory of the spool holding a `job.json`:
+//!
+//! ```json
+//! {"config": "xixi.rkyv", "user_cred": "user", "route": "route.txt", "prove": ["--profile", "small-memory"]}
+//! ```
+//!
+//! Paths are relative to the job dir and may not leave it. `prove` takes the
+//! same options as `host prove`, except `--anonymous`: an anonymous job names
+//! its reveal file in `"anonymous": "reveal.json"` instead. Write `job.json`
+//! last (or rename it into place); the daemon picks up a job once that file
+//! parses. Results are written next to it as `proof.bin` and `result.json`.
+//!
+//! Job states live in `<spool>/jobs.json`. Jobs found running after a
+//! restart are queued again, and resume from their prove checkpoints. A job
+//! that panics is marked failed like one that returns an error.
+
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use xixi_zk::ProveOptions;
+
+const JOB_FILE: &str = "job.json";
+const STATE_FILE: &str = "jobs.json";
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Deserialize)]
+struct JobSpec {
+    config: PathBuf,
+    user_cred: PathBuf,
+    route: PathBuf,
+    #[serde(default)]
+    prove: Vec<String>,
+    /// Reveal file of an anonymous proof (`host prove --anonymous`)
+    #[serde(default)]
+    anonymous: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum JobStatus {
+    Queued,
+    Running,
+    Failed,
+    Done,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct JobRecord {
+    status: JobStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// Unix seconds
+    submitted: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    started: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    finished: Option<u64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct JobState {
+    jobs: BTreeMap<String, JobRecord>,
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+impl JobState {
+    fn load(spool: &Path) -> Result<Self> {
+        match fs::read(spool.join(STATE_FILE)) {
+            Ok(bytes) => serde_json::from_slice(&bytes).context("Corrupt job state file"),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    fn save(&self, spool: &Path) -> Result<()> {
+        let path = spool.join(STATE_FILE);
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    /// Queue jobs again that were running when the daemon stopped
+    fn requeue_interrupted(&mut self) {
+        for (id, record) in self.jobs.iter_mut() {
+            if record.status == JobStatus::Running {
+                eprintln!("[daemon] {} was interrupted, queued again", id);
+                record.status = JobStatus::Queued;
+            }
+        }
+    }
+
+    /// Queue job dirs that have a readable `job.json` and are not known yet
+    fn scan(&mut self, spool: &Path) -> Result<bool> {
+        let mut changed = false;
+        for entry in fs::read_dir(spool)? {
+            let path = entry?.path();
+            let Some(id) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            if !path.is_dir() || self.jobs.contains_key(id) || read_spec(&path).is_err() {
+                continue;
+            }
+            eprintln!("[daemon] queued {}", id);
+            self.jobs.insert(
+                id.to_string(),
+                JobRecord { status: JobStatus::Queued, error: None, submitted: now(), started: None, finished: None },
+            );
+            changed = true;
+        }
+        Ok(changed)
+    }
+}
+
+fn read_spec(job_dir: &Path) -> Result<JobSpec> {
+    Ok(serde_json::from_slice(&fs::read(job_dir.join(JOB_FILE))?)?)
+}
+
+/// `path` from a job spec inside `job_dir`; absolute paths and `..` are
+/// refused, so a job cannot read or write outside its directory
+fn job_path(job_dir: &Path, path: &Path) -> Result<String> {
+    if !path.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+        bail!("Job path {} must be relative to the job directory", path.display());
+    }
+    Ok(job_dir.join(path).to_string_lossy().into_owned())
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    match (panic.downcast_ref::<&str>(), panic.downcast_ref::<String>()) {
+        (Some(message), _) => message,
+        (_, Some(message)) => message,
+        _ => "unknown cause",
+    }
+}
+
+fn run_job(job_dir: &Path) -> Result<()> {
+    let spec = read_spec(job_dir)?;
+    if spec.prove.iter().any(|a| a == "--anonymous") {
+        bail!("Give the reveal file of an anonymous job as \"anonymous\" in {}, not in \"prove\"", JOB_FILE);
+    }
+    let mut opts = ProveOptions::from_args(&spec.prove)?;
+    opts.progress = false;
+
+    let output = job_dir.join("proof.bin");
+    let reveal = spec.anonymous.as_deref().map(|p| job_path(job_dir, p)).transpose()?;
+    let output_json = crate::prove_files(
+        &job_path(job_dir, &spec.config)?,
+        &job_path(job_dir, &spec.user_cred)?,
+        &job_path(job_dir, &spec.route)?,
+        &output.to_string_lossy(),
+        reveal.as_deref(),
+        &opts,
+    )?;
+    fs::write(job_dir.join("result.json"), serde_json::to_string_pretty(&output_json)?)?;
+    Ok(())
+}
+
+/// Run `job`, turning a panic into an error: a job that panics must still
+/// report back, or it stays running
+fn catch_panic(job: impl FnOnce() -> Result<()>) -> Result<()> {
+    panic::catch_unwind(AssertUnwindSafe(job))
+        .unwrap_or_else(|panic| Err(anyhow!("Prover panicked: {}", panic_message(&*panic))))
+}
+
+/// Entry of `host daemon <spool> [--concurrency N]`
+pub fn run(spool: &Path, concurrency: usize) -> Result<()> {
+    if concurrency == 0 {
+        bail!("Concurrency must be at least 1");
+    }
+    fs::create_dir_all(spool)?;
+
+    let mut state = JobState::load(spool)?;
+    state.requeue_interrupted();
+    state.save(spool)?;
+
+    let (done_tx, done_rx) = mpsc::channel::<(String, Result<()>)>();
+    let mut running = 0;
+    eprintln!("[daemon] watching {} with concurrency {}", spool.display(), concurrency);
+    loop {
+        let mut changed = state.scan(spool)?;
+
+        while running < concurrency {
+            let Some((id, record)) = state.jobs.iter_mut().find(|(_, r)| r.status == JobStatus::Queued) else {
+                break;
+            };
+            record.status = JobStatus::Running;
+            record.started = Some(now());
+            changed = true;
+            running += 1;
+
+            eprintln!("[daemon] proving {}", id);
+            let id = id.clone();
+            let job_dir = spool.join(&id);
+            let done_tx = done_tx.clone();
+            std::thread::spawn(move || {
+                let result = catch_panic(|| run_job(&job_dir));
+                let _ = done_tx.send((id, result));
+            });
+        }
+        if changed {
+            state.save(spool)?;
+        }
+
+        if let Ok((id, result)) = done_rx.recv_timeout(POLL_INTERVAL) {
+            running -= 1;
+            let record = state.jobs.get_mut(&id).context("Finished job vanished from state")?;
+            record.finished = Some(now());
+            match result {
+                Ok(()) => {
+                    eprintln!("[daemon] {} done", id);
+                    record.status = JobStatus::Done;
+                    record.error = None;
+                }
+                Err(e) => {
+                    eprintln!("[daemon] {} failed: {:#}", id, e);
+                    record.status = JobStatus::Failed;
+                    record.error = Some(format!("{:#}", e));
+                }
+            }
+            state.save(spool)?;
+        }
+    }
+}
+
+/// Entry of `host jobs <spool>`
+pub fn list(spool: &Path) -> Result<()> {
+    let state = JobState::load(spool)?;
+    if state.jobs.is_empty() {
+        println!("No jobs in {}", spool.display());
+        return Ok(());
+    }
+    println!("{:<24} {:<8} {:>12} {:>12}  ERROR", "JOB", "STATUS", "SUBMITTED", "FINISHED");
+    for (id, record) in &state.jobs {
+        let status = serde_json::to_value(record.status)?;
+        println!(
+            "{:<24} {:<8} {:>12} {:>12}  {}",
+            id,
+            status.as_str().unwrap_or_default(),
+            record.submitted,
+            record.finished.map(|t| t.to_string()).unwrap_or_else(|| "-".into()),
+            record.error.as_deref().unwrap_or("")
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spool(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("xixi-zk-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn add_job(spool: &Path, id: &str, spec: &str) {
+        fs::create_dir_all(spool.join(id)).unwrap();
+        fs::write(spool.join(id).join(JOB_FILE), spec).unwrap();
+    }
+
+    #[test]
+    fn scan_queues_complete_jobs_once() {
+        let spool = spool("daemon-scan");
+        add_job(&spool, "a", r#"{"config": "c", "user_cred": "u", "route": "r"}"#);
+        // Still being written
+        add_job(&spool, "b", r#"{"config": "c""#);
+        fs::write(spool.join("loose.json"), "{}").unwrap();
+
+        let mut state = JobState::default();
+        assert!(state.scan(&spool).unwrap());
+        assert_eq!(state.jobs.keys().collect::<Vec<_>>(), ["a"]);
+        assert_eq!(state.jobs["a"].status, JobStatus::Queued);
+        assert!(!state.scan(&spool).unwrap());
+
+        add_job(&spool, "b", r#"{"config": "c", "user_cred": "u", "route": "r", "prove": ["--compress"]}"#);
+        assert!(state.scan(&spool).unwrap());
+        assert_eq!(state.jobs.len(), 2);
+        fs::remove_dir_all(&spool).unwrap();
+    }
+
+    #[test]
+    fn restart_requeues_running_jobs() {
+        let spool = spool("daemon-restart");
+        let record = |status| JobRecord { status, error: None, submitted: 1, started: Some(2), finished: None };
+        let mut state = JobState::default();
+        state.jobs.insert("running".into(), record(JobStatus::Running));
+        state.jobs.insert("done".into(), record(JobStatus::Done));
+        state.save(&spool).unwrap();
+
+        let mut state = JobState::load(&spool).unwrap();
+        state.requeue_interrupted();
+        assert_eq!(state.jobs["running"].status, JobStatus::Queued);
+        assert_eq!(state.jobs["done"].status, JobStatus::Done);
+        fs::remove_dir_all(&spool).unwrap();
+    }
+
+    #[test]
+    fn panicking_job_fails() {
+        let error = catch_panic(|| panic!("out of memory")).unwrap_err();
+        assert_eq!(error.to_string(), "Prover panicked: out of memory");
+        let error = catch_panic(|| panic!("segment {}", 3)).unwrap_err();
+        assert_eq!(error.to_string(), "Prover panicked: segment 3");
+        assert!(catch_panic(|| Ok(())).is_ok());
+    }
+
+    #[test]
+    fn job_paths_stay_in_the_job_dir() {
+        let dir = Path::new("/spool/a");
+        assert_eq!(job_path(dir, Path::new("route.txt")).unwrap(), "/spool/a/route.txt");
+        assert_eq!(job_path(dir, Path::new("./in/route.txt")).unwrap(), "/spool/a/./in/route.txt");
+        assert!(job_path(dir, Path::new("/etc/passwd")).is_err());
+        assert!(job_path(dir, Path::new("../b/user")).is_err());
+        assert!(job_path(dir, Path::new("in/../../b/user")).is_err());
+
+        // Refused before anything is proven
+        let spool = spool("daemon-paths");
+        add_job(&spool, "a", r#"{"config": "c", "user_cred": "../b/user", "route": "r"}"#);
+        let error = run_job(&spool.join("a")).unwrap_err();
+        assert!(error.to_string().contains("relative to the job directory"));
+        fs::remove_dir_all(&spool).unwrap();
+    }
+}
@@ -0,0 +1,32 @@
+use xixi_verify::policy::Reason;
+
+/// Errors of the library API
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("{what} too large: {size} bytes, limit {limit}")]
+    TooLarge { what: &'static str, size: usize, limit: usize },
+    #[error("invalid route: {0}")]
+    InvalidRoute(String),
+    #[error("invalid config: {0}")]
+    InvalidConfig(String),
+    /// Guest image registry could not be read, or has no usable entry
+    #[error("guest image registry: {0:#}")]
+    Registry(anyhow::Error),
+    #[error("proving failed: {0:#}")]
+    Prove(anyhow::Error),
+    /// Proofs that cannot go into one leaderboard
+    #[error("cannot aggregate: {0}")]
+    Aggregate(String),
+    /// Chunk proofs that do not form a chain
+    #[error("cannot chain: {0}")]
+    Chain(String),
+    /// Proof did not pass verification or the policy
+    #[error("proof rejected ({}): {message}", reason.as_str())]
+    Rejected { reason: Reason, message: String },
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
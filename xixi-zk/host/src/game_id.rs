@@ -0,0 +1,58 @@
+//! `host game-id`: game IDs independent of the rkyv layout
+//!
+//! Computes the game ID (SHA-256 of the canonical config encoding) of JSON
+//! and rkyv configs, and checks they all agree. A JSON config is encoded
+//! from its parsed fields, an rkyv config straight from the archive as the
+//! guest does, so agreement shows the rkyv file holds the JSON game.
+
+use std::fs;
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use xixi_core::GameConfig;
+
+fn describe(path: &str) -> Result<([u8; 32], Value)> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read config {}", path))?;
+    let is_json = bytes.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{');
+    if is_json {
+        let text = std::str::from_utf8(&bytes).with_context(|| format!("Invalid UTF-8 in {}", path))?;
+        let config = GameConfig::from_json(text).with_context(|| format!("Invalid JSON config {}", path))?;
+        let game_id = xixi_zk::game_id(&config);
+        Ok((game_id, json!({"path": path, "format": "json", "game_id": hex::encode(game_id)})))
+    } else {
+        let game_id = xixi_zk::game_id_from_rkyv(&bytes).with_context(|| format!("Invalid rkyv config {}", path))?;
+        let info = json!({
+            "path": path,
+            "format": "rkyv",
+            "game_id": hex::encode(game_id),
+            // Changes with the rkyv layout, unlike the game ID
+            "config_hash": hex::encode(Sha256::digest(&bytes)),
+        });
+        Ok((game_id, info))
+    }
+}
+
+/// Entry of `host game-id <config.json|config.rkyv>...`
+///
+/// Returns whether every config has the same game ID.
+pub fn run(paths: &[String]) -> Result<bool> {
+    let mut ids = Vec::with_capacity(paths.len());
+    let mut configs = Vec::with_capacity(paths.len());
+    for path in paths {
+        let (game_id, info) = describe(path)?;
+        ids.push(game_id);
+        configs.push(info);
+    }
+
+    let agree = ids.windows(2).all(|w| w[0] == w[1]);
+    if !agree {
+        eprintln!("Mismatch: the configs are not the same game");
+    }
+    let report = json!({
+        "configs": configs,
+        "status": if agree { "match" } else { "mismatch" },
+    });
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(agree)
+}
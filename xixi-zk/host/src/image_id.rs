@@ -0,0 +1,202 @@
+//! `host image-id`: reproducible build check
+//!
+//! Computes the image IDs of the compiled-in guest and of an optional ELF,
+//! and compares them with the newest archived ELF in the registry. The JSON
+//! report can be published to attest a reproduced build.
+//!
+//! It also checks `xixi-zk-image/current_image_id`, the ID `xixi-verify`
+//! accepts besides the registry; `--update` rewrites it from a docker build.
+//! `--register <version>` releases the compiled-in guest: it archives the
+//! ELF next to the registry, adds its entry and updates the current ID.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use methods::{GUEST_BUILD_MODE, XIXI_VERIFIER_ELF, XIXI_VERIFIER_ID};
+use risc0_zkvm::{compute_image_id, sha::Digest};
+use serde_json::{json, Value};
+use sha2::{Digest as _, Sha256};
+use xixi_verify::registry::{parse_image_id_file, ImageEntry, Registry, IMAGE_DIR};
+
+fn describe(elf: &[u8], path: Option<&Path>, registry: &Registry) -> Result<(Digest, Value)> {
+    let image_id = compute_image_id(elf)?;
+    let info = json!({
+        "path": path.map(|p| p.display().to_string()),
+        "image_id": image_id.to_string(),
+        "sha256": hex::encode(Sha256::digest(elf)),
+        "size": elf.len(),
+        "registered_version": registry.find_id(&image_id).map(|entry| entry.version.clone()),
+    });
+    Ok((image_id, info))
+}
+
+fn read_elf(path: &Path) -> Result<Vec<u8>> {
+    fs::read(path).with_context(|| format!("Failed to read ELF {}", path.display()))
+}
+
+fn require_docker_build() -> Result<()> {
+    if GUEST_BUILD_MODE != "docker" {
+        bail!("The guest was built in {} mode; only a docker build gives the image ID to publish", GUEST_BUILD_MODE);
+    }
+    Ok(())
+}
+
+/// Write the compiled-in image ID to `xixi-zk-image/current_image_id`
+fn update_current(image_id: Digest) -> Result<()> {
+    require_docker_build()?;
+    let path = Path::new(IMAGE_DIR).join("current_image_id");
+    fs::write(&path, format!("{}\n", image_id)).with_context(|| format!("Failed to write {}", path.display()))?;
+    eprintln!("Wrote {} to {}, rebuild xixi-verify to accept it", image_id, path.display());
+    Ok(())
+}
+
+/// Archive the compiled-in guest as `version` and add it to the registry;
+/// returns the archived ELF
+fn register(image_id: Digest, version: &str, released: Option<String>, notes: Option<String>) -> Result<PathBuf> {
+    require_docker_build()?;
+    let registry_path = Registry::path();
+    let name = format!("xixi_verifier-{}.bin", version);
+    let elf_path = registry_path.parent().unwrap_or(Path::new(".")).join(&name);
+    if elf_path.exists() {
+        bail!("{} already exists", elf_path.display());
+    }
+    let entry = ImageEntry {
+        version: version.to_string(),
+        image_id: image_id.to_string(),
+        elf: Some(PathBuf::from(name)),
+        released,
+        risc0: Some(risc0_zkvm::VERSION.to_string()),
+        rust: None,
+        notes,
+    };
+    Registry::append(&registry_path, entry)?;
+    fs::write(&elf_path, XIXI_VERIFIER_ELF).with_context(|| format!("Failed to write {}", elf_path.display()))?;
+    eprintln!("Registered guest {} as {} in {}", image_id, version, registry_path.display());
+    Ok(elf_path)
+}
+
+/// Entry of `host image-id [--elf <path>] [--reference <path>] [--update]
+/// [--register <version> [--released <date>] [--notes <text>]]`
+///
+/// Returns whether every image matched the reference.
+pub fn run(args: &[String]) -> Result<bool> {
+    let mut elf_path = None;
+    let mut update = false;
+    let mut reference_path = None;
+    let (mut version, mut released, mut notes) = (None, None, None);
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().with_context(|| format!("{} requires a value", arg));
+        match arg.as_str() {
+            "--elf" => elf_path = Some(PathBuf::from(value()?)),
+            "--reference" => reference_path = Some(PathBuf::from(value()?)),
+            "--update" => update = true,
+            "--register" => version = Some(value()?.clone()),
+            "--released" => released = Some(value()?.clone()),
+            "--notes" => notes = Some(value()?.clone()),
+            _ => bail!("Unknown image-id option '{}'", arg),
+        }
+    }
+    if version.is_none() && (released.is_some() || notes.is_some()) {
+        bail!("--released and --notes need --register");
+    }
+
+    let registry = Registry::load()?;
+    let (compiled_id, compiled) = describe(XIXI_VERIFIER_ELF, None, &registry)?;
+    if compiled_id != Digest::from(XIXI_VERIFIER_ID) {
+        bail!("Compiled-in ELF has image ID {}, but XIXI_VERIFIER_ID is {}", compiled_id, Digest::from(XIXI_VERIFIER_ID));
+    }
+    if let Some(version) = &version {
+        let archived = register(compiled_id, version, released, notes)?;
+        reference_path.get_or_insert(archived);
+        update = true;
+    }
+    if update {
+        update_current(compiled_id)?;
+    }
+
+    // Newest archived release, unless given
+    let reference_path = match reference_path {
+        Some(path) => path,
+        None => registry
+            .images
+            .iter()
+            .rev()
+            .find_map(|entry| registry.elf_path(entry))
+            .unwrap_or_else(|| Path::new(IMAGE_DIR).join("xixi_verifier.bin")),
+    };
+    let (reference_id, reference) = describe(&read_elf(&reference_path)?, Some(&reference_path), &registry)?;
+    // From disk: `--update` may just have rewritten it
+    let current_path = Path::new(IMAGE_DIR).join("current_image_id");
+    let current_id = parse_image_id_file(&fs::read_to_string(&current_path).unwrap_or_default())?;
+
+    let mut report = json!({
+        "risc0_version": risc0_zkvm::VERSION,
+        "guest_build_mode": GUEST_BUILD_MODE,
+        "reference": reference,
+        "compiled": compiled,
+        "compiled_matches": compiled_id == reference_id,
+        "current_image_id": current_id.map(|id| id.to_string()),
+    });
+    if current_id != Some(compiled_id) {
+        eprintln!("Note: xixi-zk-image/current_image_id is not the compiled-in guest, run image-id --update after a docker build");
+    }
+    let mut ok = compiled_id == reference_id;
+    if !ok {
+        eprintln!("Mismatch: compiled-in guest {} differs from reference {}", compiled_id, reference_id);
+    }
+    if let Some(path) = &elf_path {
+        let (elf_id, elf) = describe(&read_elf(path)?, Some(path), &registry)?;
+        report["elf"] = elf;
+        report["elf_matches"] = (elf_id == reference_id).into();
+        if elf_id != reference_id {
+            eprintln!("Mismatch: {} has image ID {}, reference is {}", path.display(), elf_id, reference_id);
+            ok = false;
+        }
+    }
+    report["status"] = if ok { "reproduced" } else { "mismatch" }.into();
+
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(ok)
+}
+
+#[cfg(test)]
+mod tests {
+    use xixi_verify::registry::current_image_id;
+
+    use super::*;
+
+    /// `xixi-verify` must accept proofs from the guest in this tree
+    #[test]
+    #[ignore = "needs the guest built with docker, run with `cargo test -p xixi-zk -- --ignored`"]
+    fn current_image_id_is_up_to_date() {
+        assert_eq!(GUEST_BUILD_MODE, "docker", "the guest was not built with docker, its image ID is not reproducible");
+        let current = current_image_id().unwrap();
+        assert_eq!(current, Some(Digest::from(XIXI_VERIFIER_ID)), "run `host image-id --update` and commit the result");
+    }
+
+    #[test]
+    fn describes_reference() {
+        let path = Path::new(IMAGE_DIR).join("xixi_verifier.bin");
+        let elf = read_elf(&path).unwrap();
+        let (image_id, info) = describe(&elf, Some(&path), &Registry::load().unwrap()).unwrap();
+        assert_eq!(image_id.to_string(), "5be9bd21c4e75c2b4ccef0e7b6308f5ad5352940b3ccd83aaf22a2a229f67b61");
+        assert_eq!(info["registered_version"], "0.1.0");
+        assert_eq!(info["size"], elf.len());
+        assert_eq!(info["sha256"], hex::encode(Sha256::digest(&elf)));
+    }
+
+    #[test]
+    fn rejects_bad_options() {
+        for bad in [&["--elfs", "x"][..], &["--elf"], &["--notes", "x"], &["--released", "2026-01-01"]] {
+            let args: Vec<String> = bad.iter().map(|s| s.to_string()).collect();
+            assert!(run(&args).is_err(), "{:?}", bad);
+        }
+        // Only a reproducible build may be published
+        if GUEST_BUILD_MODE != "docker" {
+            assert!(update_current(Digest::ZERO).is_err());
+            assert!(register(Digest::ZERO, "9.9.9", None, None).is_err());
+        }
+    }
+}
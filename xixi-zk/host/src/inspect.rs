@@ -0,0 +1,136 @@
+//! `host inspect`: show what a proof or config file contains
+//!
+//! Nothing is verified here; a proof is only decoded, so this is safe to run
+//! on files received from anyone before deciding whether to trust them.
+
+use std::fs;
+
+use anyhow::{bail, Result};
+use methods::XIXI_AGGREGATOR_ID;
+use risc0_zkvm::{sha::Digestible, InnerReceipt};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use xixi_core::{GameConfig, MajorDesc};
+use xixi_verify::envelope::{ProofEnvelope, MAGIC};
+use xixi_verify::policy::disclosed_json;
+use xixi_verify::registry::Registry;
+use xixi_verify::reveal::credential_commitment;
+
+fn inspect_proof(proof: &ProofEnvelope) -> Result<Value> {
+    let receipt = &proof.receipt;
+    let kind = match &receipt.inner {
+        InnerReceipt::Composite(composite) => format!("composite ({} segments)", composite.segments.len()),
+        InnerReceipt::Succinct(_) => "succinct".to_string(),
+        InnerReceipt::Groth16(_) => "groth16".to_string(),
+        InnerReceipt::Fake(_) => "fake (dev mode, not a proof)".to_string(),
+        _ => "unknown".to_string(),
+    };
+    // Image ID the seal claims to prove, as opposed to the one in the header
+    let claimed_image_id = receipt
+        .claim()
+        .ok()
+        .and_then(|claim| claim.as_value().ok().map(|claim| claim.pre.digest()));
+    let registry = Registry::load()?;
+    let guest = claimed_image_id.and_then(|id| registry.find_id(&id));
+
+    let mut info = json!({
+        "type": "proof",
+        "format_version": proof.version,
+        "risc0_version": proof.risc0_version,
+        "compressed": proof.compressed,
+        "receipt_kind": kind,
+        "seal_size": receipt.seal_size(),
+        "header_image_id": proof.image_id.map(|id| id.to_string()),
+        "claimed_image_id": claimed_image_id.map(|id| id.to_string()),
+        "registered_guest": serde_json::to_value(guest)?,
+    });
+    if proof.is_chunk() {
+        info["chunk"] = xixi_zk::chunk_json(&proof.chunk_claim()?);
+        return Ok(info);
+    }
+    if proof.image_id == Some(XIXI_AGGREGATOR_ID.into()) {
+        info["leaderboard"] = xixi_zk::leaderboard_json(&proof.leaderboard()?);
+        return Ok(info);
+    }
+
+    let output = proof.output()?;
+    info["output"] = json!({
+        "config_hash": hex::encode(output.config_hash),
+        "game_id": output.game_id.map(hex::encode),
+        "user_cred_hash": hex::encode(output.user_cred_hash),
+        "scores": output.scores,
+        "chunk_image_id": output.chunk_image_id.map(hex::encode),
+        "challenge": output.challenge,
+        "route_commitment": output.route_commitment.map(hex::encode),
+        "route_nullifier": output.route_nullifier.map(hex::encode),
+        "judge_key": output.route_ciphertext.as_ref().map(|c| hex::encode(c.judge_key)),
+        "score_threshold": output.score_threshold,
+        "disclosed": disclosed_json(&output.disclosed),
+        "category": output.category,
+        "route_steps": output.route_steps,
+    });
+    if output.route_ciphertext.is_some() {
+        info["route_ciphertext_size"] = proof.route_ciphertext.len().into();
+    }
+    info["signed"] = (!proof.signature.is_empty()).into();
+    match &proof.user_cred {
+        Some(cred) => {
+            info["usercred"] = String::from_utf8_lossy(cred).into();
+            info["salted"] = (!proof.cred_salt.is_empty()).into();
+            info["cred_hash_matches"] = (credential_commitment(cred, &proof.cred_salt) == output.user_cred_hash).into();
+        }
+        // Anonymous proof, the credential is in its reveal file
+        None => info["usercred"] = Value::Null,
+    }
+    Ok(info)
+}
+
+fn inspect_config(bytes: &[u8], config: &GameConfig) -> Value {
+    let enemy_nodes = config.major_desc.iter().filter(|d| matches!(d, MajorDesc::Enemy(_))).count();
+    let enemies = &config.enemy_data;
+    let count = |f: fn(&xixi_core::Enemy) -> bool| enemies.iter().filter(|e| f(e)).count();
+    let max = |f: fn(&xixi_core::Enemy) -> i32| enemies.iter().map(f).max().unwrap_or(0);
+
+    json!({
+        "type": "config",
+        "size": bytes.len(),
+        // Same digest the guest commits as `config_hash`
+        "config_hash": hex::encode(Sha256::digest(bytes)),
+        // Same as the guest commits as `game_id`, independent of rkyv
+        "game_id": hex::encode(xixi_zk::game_id(config)),
+        "nodes": config.major_desc.len(),
+        "edges": config.major_adj.iter().map(Vec::len).sum::<usize>(),
+        "enemy_nodes": enemy_nodes,
+        "delta_nodes": config.major_desc.len() - enemy_nodes,
+        "minors": config.minor_desc.len(),
+        "minor_links": config.major_minor_adj.iter().map(Vec::len).sum::<usize>(),
+        "enemies": enemies.len(),
+        "enemy_stats": {
+            "max_hp": max(|e| e.hp),
+            "max_atk": max(|e| e.atk),
+            "max_def": max(|e| e.def),
+            "magic": count(|e| e.magic),
+            "solid": count(|e| e.solid),
+            "speedy": count(|e| e.speedy),
+            "nobomb": count(|e| e.nobomb),
+        },
+        "levelups": config.levelup_desc.len(),
+        "init_stat": serde_json::to_value(&config.init_stat).unwrap_or_default(),
+    })
+}
+
+/// Entry of `host inspect <file>`
+pub fn run(path: &str) -> Result<()> {
+    let bytes = fs::read(path)?;
+    let info = if bytes.starts_with(MAGIC) {
+        inspect_proof(&ProofEnvelope::from_bytes(&bytes)?)?
+    } else if let Some(config) = GameConfig::try_from_rkyv(&bytes) {
+        inspect_config(&bytes, &config)
+    } else if let Ok(proof) = ProofEnvelope::from_bytes(&bytes) {
+        inspect_proof(&proof)?
+    } else {
+        bail!("{} is neither a proof file nor an rkyv game config", path);
+    };
+    println!("{}", serde_json::to_string_pretty(&info)?);
+    Ok(())
+}
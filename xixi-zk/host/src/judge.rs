@@ -0,0 +1,44 @@
+//! `host judge-keygen` and `host judge-decrypt`: routes sealed to a judge
+//!
+//! A proof made with `prove --judge <public key>` carries its route
+//! encrypted to that key, and the guest attests the ciphertext holds the
+//! proven route. Only the judge's secret key opens it.
+
+use std::fs;
+
+use anyhow::{Context, Result};
+use rand_core::{OsRng, RngCore};
+use xixi_core::judge;
+use xixi_zk::{Policy, ProofEnvelope};
+
+use crate::keys::write_secret;
+
+const KEY_PREFIX: &str = "x25519:";
+
+/// Entry of `host judge-keygen <key_file>`
+pub fn keygen(key_path: &str) -> Result<()> {
+    let mut secret = [0u8; 32];
+    OsRng.fill_bytes(&mut secret);
+    write_secret(key_path, &format!("{}{}\n", KEY_PREFIX, hex::encode(secret)))?;
+    println!("Judge public key: {}", hex::encode(judge::public_key(&secret)));
+    println!("Secret key written to {}", key_path);
+    Ok(())
+}
+
+fn load_secret(path: &str) -> Result<[u8; 32]> {
+    let text = fs::read_to_string(path).with_context(|| format!("Failed to read key {}", path))?;
+    let secret = text.trim_end().strip_prefix(KEY_PREFIX).context("Judge key file must hold x25519:<hex secret>")?;
+    hex::decode(secret).ok().and_then(|s| s.try_into().ok()).context("Secret must be 32 hex bytes")
+}
+
+/// Entry of `host judge-decrypt <proof.bin> <key_file> [policy options]`:
+/// prints the route as JSON
+pub fn decrypt(proof_path: &str, key_path: &str, policy_args: &[String]) -> Result<()> {
+    let secret = load_secret(key_path)?;
+    let policy = Policy::from_args(policy_args)?;
+    let proof = ProofEnvelope::read(proof_path)?;
+    let route_bytes = xixi_zk::judge_decrypt(&proof, &policy, &secret)?;
+    let route: Vec<u32> = route_bytes.as_chunks().0.iter().map(|word| u32::from_le_bytes(*word)).collect();
+    println!("{}", serde_json::to_string(&route)?);
+    Ok(())
+}
@@ -0,0 +1,57 @@
+//! `host keygen` and `host sign`: public-key identities
+//!
+//! `keygen` writes a secret key file and the matching credential file to
+//! prove with; `sign` adds the signature a key credential needs to verify.
+//! Signing is separate from proving, so a proving service never needs the key.
+
+use std::fs;
+use std::io::Write;
+
+use anyhow::{Context, Result};
+use rand_core::{OsRng, RngCore};
+use xixi_zk::{ProofEnvelope, SigningKey};
+
+/// Entry of `host keygen <ed25519|secp256k1> <key_file> <cred_file>`
+pub fn keygen(kind: &str, key_path: &str, cred_path: &str) -> Result<()> {
+    // Retry the rare secret that is not a secp256k1 scalar
+    let key = loop {
+        let mut secret = [0u8; 32];
+        OsRng.fill_bytes(&mut secret);
+        match SigningKey::from_secret(kind, &secret) {
+            Ok(key) => break key,
+            Err(_) if kind == "secp256k1" => continue,
+            Err(e) => return Err(e),
+        }
+    };
+
+    write_secret(key_path, &key.to_file_string())?;
+
+    let credential = key.identity().to_credential();
+    fs::write(cred_path, &credential)?;
+    println!("Identity: {}", credential.trim_end());
+    println!("Secret key written to {}, credential to {}", key_path, cred_path);
+    Ok(())
+}
+
+/// Create a key file readable by the owner only, never overwriting one
+pub fn write_secret(path: &str, contents: &str) -> Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(path)
+        .with_context(|| format!("Failed to create key file {} (it must not exist yet)", path))?
+        .write_all(contents.as_bytes())?;
+    Ok(())
+}
+
+/// Entry of `host sign <proof.bin> <key_file>`: signs the proof in place
+pub fn sign(proof_path: &str, key_path: &str) -> Result<()> {
+    let key = SigningKey::load(key_path)?;
+    let mut proof = ProofEnvelope::read(proof_path)?;
+    xixi_zk::sign(&mut proof, &key)?;
+    proof.write(proof_path)?;
+    println!("Signed {} as {}", proof_path, key.identity().to_credential().trim_end());
+    Ok(())
+}
@@ -0,0 +1,348 @@
+//! Prove and verify mota routes with RISC Zero
+//!
+//! Library behind the `host` CLI, for services that want to prove or check
+//! routes without shelling out:
+//!
+//! ```no_run
+//! # fn main() -> xixi_zk::Result<()> {
+//! let config = xixi_zk::json_to_rkyv(&std::fs::read_to_string("xixi.compact.json")?)?;
+//! let route = xixi_zk::route_to_bytes(&std::fs::read("route.txt")?)?;
+//! let proof = xixi_zk::prove(&config, b"user3266\n", &route, &xixi_zk::ProveOptions::default())?;
+//! let claim = xixi_zk::verify(&proof, &xixi_zk::Policy::default())?;
+//! println!("{:?} scored {:?}", claim.user_cred, claim.scores);
+//! # Ok(())
+//! # }
+//! ```
+
+use methods::{XIXI_VERIFIER_ELF, XIXI_VERIFIER_ID};
+use rand_core::{OsRng, RngCore};
+use risc0_zkvm::ExecutorEnvBuilder;
+use sha2::{Digest, Sha256};
+use xixi_core::{judge, JudgeInput, OutputOptions, MAX_CHALLENGE_LEN, PROFILE_OFF};
+use xixi_verify::registry::Registry;
+use xixi_verify::reveal::credential_commitment;
+
+use crate::prover::GuestInput;
+
+mod aggregate;
+mod chain;
+mod checkpoint;
+mod error;
+pub mod prover;
+pub mod worker;
+
+pub use aggregate::{aggregate, leaderboard_json, verify_leaderboard};
+pub use chain::{chunk_json, join_chunks, prove_chunk, state_commitment};
+pub use error::{Error, Result};
+pub use prover::ProveOptions;
+pub use xixi_core::{Category, ChainInput, ChainState, ChunkClaim, Fact, GameConfig, Leaderboard, Output, StepSelection};
+pub use xixi_verify::envelope::ProofEnvelope;
+pub use xixi_verify::identity::{Identity, SigningKey};
+pub use xixi_verify::policy::{disclosed_json, Policy, Reason, VerifiedClaim};
+pub use xixi_verify::reveal::Reveal;
+
+/// Input size limits, far above the xixi game
+pub const MAX_CONFIG_SIZE: usize = 10_000_000;
+pub const MAX_USER_CRED_SIZE: usize = 1_000_000;
+pub const MAX_ROUTE_SIZE: usize = 1_000_000;
+
+/// Convert a route (JSON array or whitespace separated numbers) to the
+/// compact format the guest reads (little-endian u32)
+pub fn route_to_bytes(route: &[u8]) -> Result<Vec<u8>> {
+    let route: Vec<u32> = match serde_json::from_slice(route) {
+        Ok(route) => route,
+        Err(_) => std::str::from_utf8(route)
+            .map_err(|e| Error::InvalidRoute(e.to_string()))?
+            .split_whitespace()
+            .map(|s| s.parse().map_err(|e| Error::InvalidRoute(format!("bad value '{}': {}", s, e))))
+            .collect::<Result<_>>()?,
+    };
+
+    Ok(route.iter()
+        .flat_map(|n| n.to_le_bytes().to_vec())
+        .collect())
+}
+
+/// Convert a JSON game config (as made by convert_motadata.py) to rkyv bytes
+pub fn json_to_rkyv(json: &str) -> Result<Vec<u8>> {
+    let config = GameConfig::from_json(json).map_err(|e| Error::InvalidConfig(e.to_string()))?;
+    Ok(config.to_rkyv())
+}
+
+/// Game ID of a config: SHA-256 of its canonical encoding (see
+/// [`xixi_core::canonical`]), the same the guest commits as `game_id`
+pub fn game_id(config: &GameConfig) -> [u8; 32] {
+    Sha256::digest(config.canonical_bytes()).into()
+}
+
+/// Game ID of rkyv config bytes, read from the archive as the guest does
+pub fn game_id_from_rkyv(config_bytes: &[u8]) -> Result<[u8; 32]> {
+    let canonical = xixi_core::canonical::from_rkyv(config_bytes)
+        .ok_or_else(|| Error::InvalidConfig("not a valid rkyv game config".into()))?;
+    Ok(Sha256::digest(canonical).into())
+}
+
+/// Build executor env with guest inputs in the order the guest reads them
+pub fn guest_env_builder<'a>(
+    config_bytes: &[u8],
+    user_cred_hash: &[u8],
+    route_bytes: &[u8],
+    profile_mode: u32,
+    chain: &ChainInput,
+    options: &OutputOptions,
+) -> Result<ExecutorEnvBuilder<'a>> {
+    Ok(guest_input(config_bytes, user_cred_hash, route_bytes, profile_mode, chain, options)?.into_env())
+}
+
+/// Guest inputs of [`guest_env_builder`], before they go into an env
+pub(crate) fn guest_input(
+    config_bytes: &[u8],
+    user_cred_hash: &[u8],
+    route_bytes: &[u8],
+    profile_mode: u32,
+    chain: &ChainInput,
+    options: &OutputOptions,
+) -> Result<GuestInput> {
+    let mut input = GuestInput::default();
+    input
+        // Send config bytes with length prefix
+        .write(&(config_bytes.len() as u32))?
+        .write_slice(config_bytes)
+        // Send user cred hash (fixed size 32 bytes, no length prefix)
+        .write_slice(user_cred_hash)
+        // Send route bytes with length prefix
+        .write(&(route_bytes.len() as u32))?
+        .write_slice(route_bytes)
+        // Send profiling mode
+        .write(&profile_mode)?
+        // Send chained proof mode
+        .write(chain)?
+        // Send what to add to the output
+        .write(options)?;
+    Ok(input)
+}
+
+pub(crate) fn check_challenge(opts: &ProveOptions) -> Result<()> {
+    match &opts.challenge {
+        Some(challenge) => check_size("challenge", challenge.as_bytes(), MAX_CHALLENGE_LEN),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn check_size(what: &'static str, bytes: &[u8], limit: usize) -> Result<()> {
+    if bytes.len() > limit {
+        return Err(Error::TooLarge { what, size: bytes.len(), limit });
+    }
+    Ok(())
+}
+
+/// Prove that `route_bytes` (see [`route_to_bytes`]) completes the game in
+/// `config_bytes` (rkyv)
+///
+/// Checkpoints go to `opts.work_dir`, or a directory under the system temp
+/// dir named after the inputs, so an interrupted call resumes when repeated.
+pub fn prove(config_bytes: &[u8], user_cred: &[u8], route_bytes: &[u8], opts: &ProveOptions) -> Result<ProofEnvelope> {
+    prove_salted(config_bytes, user_cred, &[], route_bytes, opts)
+}
+
+/// Reveal with a fresh random salt for an anonymous proof of `user_cred`
+///
+/// Keep it before proving: [`prove_anonymous`] with the same reveal resumes
+/// an interrupted proof, a new salt starts over.
+pub fn anonymous_reveal(user_cred: &[u8]) -> Result<Reveal> {
+    let cred = std::str::from_utf8(user_cred).map_err(|e| anyhow::anyhow!("Invalid UTF-8 in user credential: {}", e))?;
+    let mut salt = [0u8; 32];
+    OsRng.fill_bytes(&mut salt);
+    Ok(Reveal::new(cred, &salt))
+}
+
+/// Like [`prove`], but the journal commits the credential of `reveal` with
+/// its salt and the proof file leaves it out; the reveal can prove it later
+///
+/// Identity credentials are refused: their signature cannot be checked while
+/// the key is withheld, so such a proof would never verify.
+pub fn prove_anonymous(
+    config_bytes: &[u8],
+    reveal: &Reveal,
+    route_bytes: &[u8],
+    opts: &ProveOptions,
+) -> Result<ProofEnvelope> {
+    let user_cred = reveal.usercred.as_bytes();
+    if Identity::from_credential(user_cred)?.is_some() {
+        return Err(anyhow::anyhow!("Identity credentials cannot prove anonymously").into());
+    }
+    let salt = hex::decode(&reveal.salt).map_err(|e| anyhow::anyhow!("Invalid salt hex in reveal: {}", e))?;
+    let mut proof = prove_salted(config_bytes, user_cred, &salt, route_bytes, opts)?;
+    proof.user_cred = None;
+    proof.cred_salt.clear();
+    Ok(proof)
+}
+
+fn prove_salted(
+    config_bytes: &[u8],
+    user_cred: &[u8],
+    salt: &[u8],
+    route_bytes: &[u8],
+    opts: &ProveOptions,
+) -> Result<ProofEnvelope> {
+    // Validate input sizes
+    check_size("config", config_bytes, MAX_CONFIG_SIZE)?;
+    check_size("user credential", user_cred, MAX_USER_CRED_SIZE)?;
+    check_size("route", route_bytes, MAX_ROUTE_SIZE)?;
+    check_challenge(opts)?;
+    let route_steps = match &opts.route_steps {
+        Some(selection) => selection.select(route_bytes).ok_or_else(|| {
+            Error::InvalidRoute("public steps must be increasing and within the route".into())
+        })?,
+        None => Vec::new(),
+    };
+    let user_cred_hash = credential_commitment(user_cred, salt);
+
+    // Guest image: the compiled-in one, or an archived one from the registry
+    let (elf, image_id) = match &opts.image {
+        Some(version) => {
+            let registry = Registry::load().map_err(Error::Registry)?;
+            let entry = registry.find_version(version).map_err(Error::Registry)?;
+            let image_id = entry.digest().map_err(Error::Registry)?;
+            (registry.load_elf(entry).map_err(Error::Registry)?, image_id)
+        }
+        None => (XIXI_VERIFIER_ELF.to_vec(), XIXI_VERIFIER_ID.into()),
+    };
+
+    let config_hash: [u8; 32] = Sha256::digest(config_bytes).into();
+    let options = OutputOptions {
+        challenge: opts.challenge.clone(),
+        route_salt: opts.route_salt,
+        // Derived from the route, so an interrupted proof resumes; the same
+        // route to the same judge gives the same ciphertext (see `judge`)
+        judge: opts.judge_key.map(|judge_key| JudgeInput {
+            judge_key,
+            sender_secret: Sha256::new()
+                .chain_update(b"xixi-zk judge sender")
+                .chain_update(judge_key)
+                .chain_update(config_hash)
+                .chain_update(route_bytes)
+                .finalize()
+                .into(),
+        }),
+        score_threshold: opts.score_threshold,
+        disclose: opts.disclose.clone(),
+        category: opts.category.clone(),
+        route_steps: opts.route_steps.clone(),
+    };
+    let builder = guest_env_builder(config_bytes, &user_cred_hash, route_bytes, PROFILE_OFF, &ChainInput::Off, &options)?;
+    let input_digest = Sha256::new()
+        .chain_update(config_bytes)
+        .chain_update(user_cred_hash)
+        .chain_update(route_bytes)
+        .chain_update(bincode::serialize(&options).map_err(anyhow::Error::from)?)
+        .finalize();
+    let default_work_dir = std::env::temp_dir().join(format!("xixi-zk-{}.work", hex::encode(&input_digest[..8])));
+    let receipt = prover::prove(builder, &elf, opts, default_work_dir, &input_digest).map_err(Error::Prove)?;
+
+    // Wrap receipt with user credential
+    let mut proof = ProofEnvelope::new(image_id, receipt, Some(user_cred.to_vec()));
+    proof.cred_salt = salt.to_vec();
+    proof.compressed = opts.compress;
+    if let Some(input) = &options.judge {
+        proof.route_ciphertext = judge::encrypt(&input.judge_key, &input.sender_secret, &config_hash, route_bytes)
+            .ok_or_else(|| Error::Prove(anyhow::anyhow!("Unusable judge key")))?;
+    }
+
+    // Registered images from before challenges ignore those inputs
+    let output = proof.output()?;
+    if output.challenge != opts.challenge {
+        return Err(Error::Prove(anyhow::anyhow!("Guest image does not support challenges")));
+    }
+    if output.route_commitment != opts.route_salt.map(|salt| route_commitment(route_bytes, &salt)) {
+        return Err(Error::Prove(anyhow::anyhow!("Guest image does not support route commitments")));
+    }
+    let ciphertext_hash = Sha256::digest(&proof.route_ciphertext).into();
+    if output.route_ciphertext.map(|c| (c.judge_key, c.ciphertext_hash)) != opts.judge_key.map(|key| (key, ciphertext_hash)) {
+        return Err(Error::Prove(anyhow::anyhow!("Guest image does not support route encryption")));
+    }
+    if output.score_threshold.map(|t| t.threshold) != opts.score_threshold {
+        return Err(Error::Prove(anyhow::anyhow!("Guest image does not support score thresholds")));
+    }
+    if !output.disclosed.iter().map(|d| d.fact).eq(opts.disclose.iter().copied()) {
+        return Err(Error::Prove(anyhow::anyhow!("Guest image does not support disclosure")));
+    }
+    if output.category != opts.category {
+        return Err(Error::Prove(anyhow::anyhow!("Guest image does not support categories")));
+    }
+    if output.route_steps != route_steps {
+        return Err(Error::Prove(anyhow::anyhow!("Guest image does not support public route steps")));
+    }
+    Ok(proof)
+}
+
+/// Route commitment the guest makes with `prove --route-salt`: SHA-256 of
+/// the salt followed by the route bytes (see [`route_to_bytes`])
+pub fn route_commitment(route_bytes: &[u8], salt: &[u8; 32]) -> [u8; 32] {
+    Sha256::new().chain_update(salt).chain_update(route_bytes).finalize().into()
+}
+
+/// Route nullifier a full proof of `route_bytes` in the game `config_bytes`
+/// commits, e.g. to look for a leaked route among submissions
+///
+/// Proofs joined from chunks of the same route commit the same nullifier.
+pub fn route_nullifier(config_bytes: &[u8], route_bytes: &[u8]) -> [u8; 32] {
+    let seed = chain::nullifier_seed(&Sha256::digest(config_bytes).into());
+    chain::extend_nullifier(seed, route_bytes)
+}
+
+/// Whether `proof` passes `policy` and committed to exactly `route_bytes`
+/// with `salt`
+pub fn check_route(proof: &ProofEnvelope, policy: &Policy, route_bytes: &[u8], salt: &[u8; 32]) -> Result<bool> {
+    let claim = verify(proof, policy)?;
+    Ok(claim.route_commitment == Some(route_commitment(route_bytes, salt)))
+}
+
+/// Decrypt the route of a proof made with `--judge`, as route bytes, after
+/// checking `proof` passes `policy`
+pub fn judge_decrypt(proof: &ProofEnvelope, policy: &Policy, judge_secret: &[u8; 32]) -> Result<Vec<u8>> {
+    let claim = verify(proof, policy)?;
+    let Some(judge_key) = claim.judge_key else {
+        return Err(anyhow::anyhow!("Proof has no route encrypted to a judge").into());
+    };
+    if judge::public_key(judge_secret) != judge_key {
+        return Err(anyhow::anyhow!("Route is encrypted to another judge ({})", hex::encode(judge_key)).into());
+    }
+    let route = judge::decrypt(judge_secret, &claim.game, &proof.route_ciphertext)
+        .ok_or_else(|| anyhow::anyhow!("Route ciphertext does not decrypt"))?;
+    Ok(route)
+}
+
+/// Sign `proof` with the key its credential names (see [`Identity`])
+pub fn sign(proof: &mut ProofEnvelope, key: &SigningKey) -> Result<()> {
+    let credential = key.identity().to_credential();
+    let Some(user_cred) = &proof.user_cred else {
+        return Err(anyhow::anyhow!("Anonymous proofs cannot be signed").into());
+    };
+    if user_cred != credential.as_bytes() {
+        return Err(anyhow::anyhow!(
+            "Proof is for credential '{}', the key is '{}'",
+            String::from_utf8_lossy(user_cred).trim_end(),
+            credential.trim_end()
+        )
+        .into());
+    }
+    proof.signature = key.sign(&xixi_verify::identity::journal_digest(&proof.receipt))?;
+    Ok(())
+}
+
+/// Verify a proof against `policy`
+///
+/// Accepts the compiled-in guest and every registered image unless the
+/// policy lists image IDs.
+pub fn verify(proof: &ProofEnvelope, policy: &Policy) -> Result<VerifiedClaim> {
+    let registry = Registry::load().map_err(Error::Registry)?;
+    let verdict = policy.verify(proof, Some(XIXI_VERIFIER_ID.into()), &registry);
+    match (verdict.claim, verdict.reason) {
+        (Some(claim), None) => Ok(claim),
+        (_, reason) => Err(Error::Rejected {
+            reason: reason.unwrap_or(Reason::InvalidProof),
+            message: verdict.message,
+        }),
+    }
+}
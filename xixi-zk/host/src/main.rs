@@ -1,42 +1,80 @@
 use std::fs;
+use std::path::Path;
 
 use anyhow::{bail, Context, Result};
-use bincode;
-use methods::{XIXI_VERIFIER_ELF, XIXI_VERIFIER_ID};
-use risc0_zkvm::{default_prover, ExecutorEnv, ProverOpts, Receipt};
-use serde_json;
-use sha2::{Sha256, Digest};
-use xixi_core::{GameConfig, Output};
-
-/// Wrapped receipt containing original user credential
-#[derive(serde::Serialize, serde::Deserialize)]
-struct WrappedReceipt {
-    receipt: Receipt,
-    user_cred: Vec<u8>,
+use xixi_zk::{disclosed_json, Error, Output, Policy, ProofEnvelope, ProveOptions, Reveal};
+
+mod bench;
+mod daemon;
+mod game_id;
+mod image_id;
+mod inspect;
+mod judge;
+mod keys;
+
+/// Prove a route and write the proof file to `output_path`
+///
+/// Returns the guest output as JSON.
+fn prove_files(
+    config_path: &str,
+    user_cred_path: &str,
+    route_path: &str,
+    output_path: &str,
+    reveal_path: Option<&str>,
+    opts: &ProveOptions,
+) -> Result<serde_json::Value> {
+    let config_bytes = fs::read(config_path).context("Failed to read config")?;
+    let user_cred = fs::read(user_cred_path).context("Failed to read user cred")?;
+    let route_bytes = xixi_zk::route_to_bytes(&fs::read(route_path).context("Failed to read route")?)?;
+
+    // Checkpoints next to the output unless given
+    let mut opts = opts.clone();
+    opts.work_dir.get_or_insert_with(|| format!("{}.work", output_path).into());
+    let proof = match reveal_path {
+        Some(reveal_path) => {
+            // Written first and reused, so a resumed proof keeps its salt
+            let reveal = if Path::new(reveal_path).exists() {
+                let reveal = Reveal::load(reveal_path)?;
+                if reveal.usercred.as_bytes() != user_cred {
+                    bail!("Reveal {} is for another credential; remove it or pick another path", reveal_path);
+                }
+                reveal
+            } else {
+                let reveal = xixi_zk::anonymous_reveal(&user_cred)?;
+                reveal.write(reveal_path)?;
+                reveal
+            };
+            xixi_zk::prove_anonymous(&config_bytes, &reveal, &route_bytes, &opts)?
+        }
+        None => xixi_zk::prove(&config_bytes, &user_cred, &route_bytes, &opts)?,
+    };
+
+    proof.write(output_path)?;
+    Ok(output_json(&proof.output()?))
 }
 
-/// Convert route JSON to compact byte format (little-endian u32)
-fn route_to_bytes(path: &str) -> Result<Vec<u8>> {
-    let bytes = fs::read(path)?;
-    let route: Vec<u32> = serde_json::from_slice(&bytes)
-        .or_else(|_| {
-            std::str::from_utf8(&bytes)?
-                .split_whitespace()
-                .map(|s| s.parse().map_err(|e| anyhow::anyhow!("Invalid route value: {}", e)))
-                .collect()
-        })?;
-    
-    Ok(route.iter()
-        .flat_map(|n| n.to_le_bytes().to_vec())
-        .collect())
+/// Guest output as printed after proving
+fn output_json(output: &Output) -> serde_json::Value {
+    serde_json::json!({
+        "config_hash": hex::encode(output.config_hash),
+        "game_id": output.game_id.map(hex::encode),
+        "user_cred_hash": hex::encode(output.user_cred_hash),
+        "scores": output.scores,
+        "challenge": output.challenge,
+        "route_commitment": output.route_commitment.map(hex::encode),
+        "route_nullifier": output.route_nullifier.map(hex::encode),
+        "judge_key": output.route_ciphertext.as_ref().map(|c| hex::encode(c.judge_key)),
+        "score_threshold": output.score_threshold,
+        "disclosed": disclosed_json(&output.disclosed),
+        "category": output.category,
+        "route_steps": output.route_steps,
+    })
 }
 
 /// Convert JSON config to rkyv binary format
 fn json_to_rkyv(json_path: &str, output_path: &str) -> Result<()> {
     let json_str = fs::read_to_string(json_path)?;
-    let config = GameConfig::from_json(&json_str)?;
-    let rkyv_bytes = config.to_rkyv();
-    fs::write(output_path, rkyv_bytes)?;
+    fs::write(output_path, xixi_zk::json_to_rkyv(&json_str)?)?;
     println!("Successfully converted {} to {}", json_path, output_path);
     Ok(())
 }
@@ -50,8 +88,24 @@ fn main() -> Result<()> {
     if args.len() < 2 {
         eprintln!("Usage:");
         eprintln!("  Convert JSON to rkyv: {} convert <input.json> <output.rkyv>", args[0]);
-        eprintln!("  Generate proof: {} prove <config.rkyv> <user_cred.txt> <route.json> <output.bin>", args[0]);
-        eprintln!("  Verify proof:   {} verify <input.bin>", args[0]);
+        eprintln!("  Generate proof: {} prove <config.rkyv> <user_cred.txt> <route.json> <output.bin> [prove options]", args[0]);
+        eprintln!("  New identity:   {} keygen <ed25519|secp256k1> <key_file> <cred_file>", args[0]);
+        eprintln!("  Sign proof:     {} sign <proof.bin> <key_file>", args[0]);
+        eprintln!("  Verify proof:   {} verify <input.bin> [policy options]", args[0]);
+        eprintln!("  Judge key:      {} judge-keygen <key_file>", args[0]);
+        eprintln!("  Judge decrypt:  {} judge-decrypt <proof.bin> <key_file> [policy options]", args[0]);
+        eprintln!("  Check route:    {} check-route <proof.bin> <route.json> <salt_hex> [policy options]", args[0]);
+        eprintln!("  Prove chunk:    {} prove-chunk <config.rkyv> <user_cred.txt> <route_chunk.json> <output.bin> [--after <previous_chunk.bin>] [prove options]", args[0]);
+        eprintln!("  Join chunks:    {} join <user_cred.txt> <output.bin> <chunk.bin>... [prove options]", args[0]);
+        eprintln!("  Aggregate:      {} aggregate <output.bin> <proof.bin>... [prove options]", args[0]);
+        eprintln!("  Leaderboard:    {} leaderboard <aggregate.bin> [--challenge <text>]", args[0]);
+        eprintln!("  Inspect file:   {} inspect <proof.bin|config.rkyv>", args[0]);
+        eprintln!("  Image ID check: {} image-id [--elf <path>] [--reference <path>] [--update] [--register <version> [--released <date>] [--notes <text>]]", args[0]);
+        eprintln!("  Game ID:        {} game-id <config.json|config.rkyv>...", args[0]);
+        eprintln!("  Prove worker:   {} worker --listen <host:port|unix:path>", args[0]);
+        eprintln!("  Prove queue:    {} daemon <spool_dir> [--concurrency <n>]", args[0]);
+        eprintln!("  List jobs:      {} jobs <spool_dir>", args[0]);
+        eprintln!("  Cycle profile:  {} bench <config.rkyv> <baseline.json> <route|dir>... [--update] [--chunks] [--tolerance <pct>]", args[0]);
         std::process::exit(1);
     }
 
@@ -64,91 +118,225 @@ fn main() -> Result<()> {
             json_to_rkyv(&args[2], &args[3])?;
         }
         "prove" => {
-            if args.len() != 6 {
-                eprintln!("Usage: {} prove <config.rkyv> <user_cred.txt> <route.json> <output.bin>", args[0]);
+            if args.len() < 6 {
+                eprintln!("Usage: {} prove <config.rkyv> <user_cred.txt> <route.json> <output.bin> [--profile <name>] [--receipt <kind>] [--segment-po2 <n>] [--threads <n>] [--work-dir <dir>] [--workers <n>] [--worker <addr>]... [--image <version>] [--challenge <text>] [--route-salt <hex>] [--judge <hex>] [--score-threshold <n>] [--disclose <facts>] [--category <id|file>] [--public-prefix <n>] [--public-steps <i,j,...>] [--anonymous <reveal.json>] [--compress] [--no-progress]", args[0]);
+                eprintln!("  profiles: {}", ProveOptions::PROFILES.join(", "));
                 std::process::exit(1);
             }
+            let mut prove_args = args[6..].to_vec();
+            let reveal_path = match prove_args.iter().position(|a| a == "--anonymous") {
+                Some(i) => {
+                    let path = prove_args.get(i + 1).context("--anonymous requires a value")?.clone();
+                    prove_args.drain(i..i + 2);
+                    Some(path)
+                }
+                None => None,
+            };
+            let opts = ProveOptions::from_args(&prove_args)?;
 
-            // Validate input sizes
-            let config_bytes = fs::read(&args[2]).context("Failed to read config")?;
-            if config_bytes.len() > 10_000_000 {
-                bail!("Config file too large");
-            }
-            
-            let user_cred = fs::read(&args[3]).context("Failed to read user cred")?;
-            if user_cred.len() > 1_000_000 {
-                bail!("User credential file too large");
-            }
-            let user_cred_hash = Sha256::digest(&user_cred);
-            
-            let route_bytes = route_to_bytes(&args[4])?;
-            if route_bytes.len() > 1_000_000 {
-                bail!("Route data too large");
-            }
-
-            let env = ExecutorEnv::builder()
-                // Send config bytes with length prefix
-                .write(&(config_bytes.len() as u32))?
-                .write_slice(&config_bytes)
-                // Send user cred hash (fixed size 32 bytes, no length prefix)
-                .write_slice(&user_cred_hash)
-                // Send route bytes with length prefix
-                .write(&(route_bytes.len() as u32))?
-                .write_slice(&route_bytes)
-                .build()?;
-
-            let prover = default_prover();
-            let receipt = prover.prove_with_opts(env, XIXI_VERIFIER_ELF, &ProverOpts::succinct())?.receipt;
-
-            // Decode and print guest output
-            let output: Output = receipt.journal.decode()?;
-            let output_json = serde_json::json!({
-                "config_hash": hex::encode(output.config_hash),
-                "user_cred_hash": hex::encode(output.user_cred_hash),
-                "scores": output.scores
-            });
+            let output_json = prove_files(&args[2], &args[3], &args[4], &args[5], reveal_path.as_deref(), &opts)?;
             println!("Guest output:");
             println!("{}", serde_json::to_string_pretty(&output_json)?);
-
-            // Wrap receipt with user credential
-            let wrapped = WrappedReceipt { receipt, user_cred };
-            fs::write(&args[5], bincode::serialize(&wrapped)?)?;
             println!("Proof written to: {}", args[5]);
+            if let Some(reveal_path) = reveal_path {
+                println!("Credential withheld, reveal written to: {}", reveal_path);
+            }
+        }
+        "keygen" => {
+            if args.len() != 5 {
+                eprintln!("Usage: {} keygen <ed25519|secp256k1> <key_file> <cred_file>", args[0]);
+                std::process::exit(1);
+            }
+            keys::keygen(&args[2], &args[3], &args[4])?;
+        }
+        "sign" => {
+            if args.len() != 4 {
+                eprintln!("Usage: {} sign <proof.bin> <key_file>", args[0]);
+                std::process::exit(1);
+            }
+            keys::sign(&args[2], &args[3])?;
         }
         "verify" => {
+            if args.len() < 3 {
+                eprintln!("Usage: {} verify <input.bin> [--policy <policy.toml>] [--game <hash>[=name]]... [--image-id <hex>]... [--min-score <n>] [--cred-pattern <regex>] [--cred-min-len <n>] [--cred-max-len <n>] [--require-signature] [--challenge <text>] [--category <id|file>] [--reveal <reveal.json>]", args[0]);
+                std::process::exit(1);
+            }
+            let code = xixi_verify::verify_cli(&args[2], &args[3..], Some(methods::XIXI_VERIFIER_ID.into()))?;
+            if code != 0 {
+                std::process::exit(code);
+            }
+        }
+        "judge-keygen" => {
             if args.len() != 3 {
-                eprintln!("Usage: {} verify <input.bin>", args[0]);
-                std::process::exit(1);
-            }
-
-            let wrapped: WrappedReceipt = bincode::deserialize(&fs::read(&args[2])?)?;
-            
-            // 1. Verify receipt
-            wrapped.receipt.verify(XIXI_VERIFIER_ID)?;
-            
-            // 2. Verify output contains expected hashes
-            let output: Output = wrapped.receipt.journal.decode()?;
-            
-            // 3. Verify user credential hash matches
-            let computed_user_hash = Sha256::digest(&wrapped.user_cred);
-            if computed_user_hash != output.user_cred_hash.into() {
-                anyhow::bail!("User credential hash mismatch");
-            }
-            
-            // Output verification result as JSON
-            let user_cred_str = String::from_utf8(wrapped.user_cred)
-                .map_err(|e| anyhow::anyhow!("Invalid UTF-8 in user credential: {}", e))?;
-            
-            let result = serde_json::json!({
-                "status": "verified",
-                "game": hex::encode(output.config_hash),
-                "usercred": user_cred_str,
-                "scores": output.scores
-            });
-            println!("{}", result.to_string());
+                eprintln!("Usage: {} judge-keygen <key_file>", args[0]);
+                std::process::exit(1);
+            }
+            judge::keygen(&args[2])?;
+        }
+        "judge-decrypt" => {
+            if args.len() < 4 {
+                eprintln!("Usage: {} judge-decrypt <proof.bin> <key_file> [policy options]", args[0]);
+                std::process::exit(1);
+            }
+            judge::decrypt(&args[2], &args[3], &args[4..])?;
+        }
+        "check-route" => {
+            if args.len() < 5 {
+                eprintln!("Usage: {} check-route <proof.bin> <route.json> <salt_hex> [policy options]", args[0]);
+                std::process::exit(1);
+            }
+            let proof = ProofEnvelope::read(&args[2])?;
+            let route_bytes = xixi_zk::route_to_bytes(&fs::read(&args[3]).context("Failed to read route")?)?;
+            let salt = hex::decode(args[4].trim()).ok().and_then(|salt| salt.try_into().ok());
+            let salt = salt.context("Salt must be 32 hex bytes")?;
+            let policy = Policy::from_args(&args[5..])?;
+
+            let matches = xixi_zk::check_route(&proof, &policy, &route_bytes, &salt)?;
+            println!(
+                "{}",
+                serde_json::json!({
+                    "status": if matches { "match" } else { "mismatch" },
+                    "route_commitment": hex::encode(xixi_zk::route_commitment(&route_bytes, &salt)),
+                })
+            );
+            if !matches {
+                eprintln!("Error: the proof did not commit to this route and salt");
+                std::process::exit(2);
+            }
+        }
+        "prove-chunk" => {
+            if args.len() < 6 {
+                eprintln!("Usage: {} prove-chunk <config.rkyv> <user_cred.txt> <route_chunk.json> <output.bin> [--after <previous_chunk.bin>] [prove options]", args[0]);
+                std::process::exit(1);
+            }
+            let mut prove_args = args[6..].to_vec();
+            let previous = match prove_args.iter().position(|a| a == "--after") {
+                Some(i) => {
+                    let path = prove_args.get(i + 1).context("--after requires a value")?.clone();
+                    prove_args.drain(i..i + 2);
+                    Some(ProofEnvelope::read(path)?)
+                }
+                None => None,
+            };
+            let mut opts = ProveOptions::from_args(&prove_args)?;
+            opts.work_dir.get_or_insert_with(|| format!("{}.work", args[5]).into());
+
+            let config_bytes = fs::read(&args[2]).context("Failed to read config")?;
+            let user_cred = fs::read(&args[3]).context("Failed to read user cred")?;
+            let route_bytes = xixi_zk::route_to_bytes(&fs::read(&args[4]).context("Failed to read route")?)?;
+            let proof = xixi_zk::prove_chunk(&config_bytes, &user_cred, previous.as_ref(), &route_bytes, &opts)?;
+            proof.write(&args[5])?;
+            println!("{}", serde_json::to_string_pretty(&xixi_zk::chunk_json(&proof.chunk_claim()?))?);
+            println!("Chunk proof written to: {}", args[5]);
+        }
+        "join" => {
+            let split = args.iter().skip(2).position(|a| a.starts_with("--")).map_or(args.len(), |i| i + 2);
+            if split < 5 {
+                eprintln!("Usage: {} join <user_cred.txt> <output.bin> <chunk.bin>... [prove options]", args[0]);
+                std::process::exit(1);
+            }
+            let opts = ProveOptions::from_args(&args[split..])?;
+            let user_cred = fs::read(&args[2]).context("Failed to read user cred")?;
+            let chunks = args[4..split].iter().map(ProofEnvelope::read).collect::<Result<Vec<_>>>()?;
+
+            let proof = xixi_zk::join_chunks(&chunks, &user_cred, &opts)?;
+            proof.write(&args[3])?;
+            println!("Guest output:");
+            println!("{}", serde_json::to_string_pretty(&output_json(&proof.output()?))?);
+            println!("Proof written to: {}", args[3]);
+        }
+        "aggregate" => {
+            let split = args.iter().skip(2).position(|a| a.starts_with("--")).map_or(args.len(), |i| i + 2);
+            if split < 4 {
+                eprintln!("Usage: {} aggregate <output.bin> <proof.bin>... [--receipt <kind>] [--segment-po2 <n>] [--threads <n>] [--compress] [--no-progress]", args[0]);
+                std::process::exit(1);
+            }
+            let opts = ProveOptions::from_args(&args[split..])?;
+            let proofs = args[3..split].iter().map(ProofEnvelope::read).collect::<Result<Vec<_>>>()?;
+
+            let proof = xixi_zk::aggregate(&proofs, &opts)?;
+            proof.write(&args[2])?;
+            println!("{}", serde_json::to_string_pretty(&xixi_zk::leaderboard_json(&proof.leaderboard()?))?);
+            println!("Leaderboard proof written to: {}", args[2]);
+        }
+        "leaderboard" => {
+            let challenge = match args.len() {
+                3 => None,
+                5 if args[3] == "--challenge" => Some(args[4].as_str()),
+                _ => {
+                    eprintln!("Usage: {} leaderboard <aggregate.bin> [--challenge <text>]", args[0]);
+                    std::process::exit(1);
+                }
+            };
+            match xixi_zk::verify_leaderboard(&ProofEnvelope::read(&args[2])?, challenge) {
+                Ok(leaderboard) => {
+                    let mut result = xixi_zk::leaderboard_json(&leaderboard);
+                    result["status"] = "verified".into();
+                    println!("{}", serde_json::to_string_pretty(&result)?);
+                }
+                Err(Error::Rejected { reason, message }) => {
+                    println!("{}", serde_json::json!({"status": "rejected", "reason": reason.as_str(), "message": message}));
+                    eprintln!("Error: {}", message);
+                    std::process::exit(reason.exit_code());
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        "inspect" => {
+            if args.len() != 3 {
+                eprintln!("Usage: {} inspect <proof.bin|config.rkyv>", args[0]);
+                std::process::exit(1);
+            }
+            inspect::run(&args[2])?;
+        }
+        "image-id" => {
+            if !image_id::run(&args[2..])? {
+                std::process::exit(2);
+            }
+        }
+        "game-id" => {
+            if args.len() < 3 {
+                eprintln!("Usage: {} game-id <config.json|config.rkyv>...", args[0]);
+                std::process::exit(1);
+            }
+            if !game_id::run(&args[2..])? {
+                std::process::exit(2);
+            }
+        }
+        "worker" => {
+            if args.len() != 4 || args[2] != "--listen" {
+                eprintln!("Usage: {} worker --listen <host:port|unix:path>", args[0]);
+                std::process::exit(1);
+            }
+            xixi_zk::worker::serve(&args[3])?;
+        }
+        "daemon" => {
+            let concurrency = match args.len() {
+                3 => 1,
+                5 if args[3] == "--concurrency" => args[4].parse().context("Invalid --concurrency")?,
+                _ => {
+                    eprintln!("Usage: {} daemon <spool_dir> [--concurrency <n>]", args[0]);
+                    std::process::exit(1);
+                }
+            };
+            daemon::run(std::path::Path::new(&args[2]), concurrency)?;
+        }
+        "jobs" => {
+            if args.len() != 3 {
+                eprintln!("Usage: {} jobs <spool_dir>", args[0]);
+                std::process::exit(1);
+            }
+            daemon::list(std::path::Path::new(&args[2]))?;
+        }
+        "bench" => {
+            if args.len() < 5 {
+                eprintln!("Usage: {} bench <config.rkyv> <baseline.json> <route|dir>... [--update] [--chunks] [--tolerance <pct>]", args[0]);
+                std::process::exit(1);
+            }
+            bench::run(&args[2], &args[3], &args[4..])?;
         }
         _ => {
-            eprintln!("Invalid command. Use 'convert', 'prove' or 'verify'");
+            eprintln!("Invalid command. Use 'convert', 'prove', 'prove-chunk', 'join', 'keygen', 'sign', 'verify', 'check-route', 'judge-keygen', 'judge-decrypt', 'aggregate', 'leaderboard', 'inspect', 'image-id', 'game-id', 'worker', 'daemon', 'jobs' or 'bench'");
             std::process::exit(1);
         }
     }
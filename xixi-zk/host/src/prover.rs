@@ -0,0 +1,620 @@
+//! Proving pipeline with named profiles and progress display
+//!
+//! Instead of a single `prove_with_opts` call, the steps are driven one by
+//! one through r0vm: execute into segments, prove each segment, then lift
+//! and join them into a succinct receipt. This way progress can be shown for
+//! a job that runs for many minutes, and every finished step is checkpointed
+//! to a work dir so an interrupted job resumes where it stopped.
+//!
+//! Segments are independent, so with workers (`--workers N` local processes
+//! or `--worker <addr>` on other machines) they are proven and lifted in
+//! parallel; the joins then run in order on the first backend.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use risc0_zkvm::sha::Digestible;
+use sha2::{Digest as _, Sha256};
+use risc0_zkvm::{
+    compute_image_id, default_prover, ApiClient, Asset, AssetRequest, Bytes, CompositeReceipt,
+    CompositeReceiptVerifierParameters, Digest, ExecutorEnv, ExecutorEnvBuilder, InnerAssumptionReceipt,
+    InnerReceipt, ProverOpts, Receipt, ReceiptClaim, ReceiptKind, SegmentReceipt, SuccinctReceipt,
+};
+
+use xixi_core::{Category, Fact, StepSelection};
+use xixi_verify::policy::load_category;
+
+use crate::checkpoint::WorkDir;
+use crate::worker::{LocalWorkers, RemoteBackend};
+
+/// Segment size used by risc0 when none is given
+const DEFAULT_SEGMENT_PO2: u32 = 20;
+
+/// Proving options, filled from a named profile and then from CLI flags
+#[derive(Debug, Clone)]
+pub struct ProveOptions {
+    pub receipt_kind: ReceiptKind,
+    /// log2 of the segment size in cycles; smaller segments need less memory
+    pub segment_po2: u32,
+    /// Prover worker threads (all cores when unset); set, proving runs in
+    /// worker processes started with `RAYON_NUM_THREADS`, so this process's
+    /// environment is left alone
+    pub threads: Option<usize>,
+    pub progress: bool,
+    /// Checkpoint directory, `<output>.work` when unset
+    pub work_dir: Option<PathBuf>,
+    /// Number of worker processes to spawn on this machine
+    pub local_workers: usize,
+    /// Addresses of already running workers (`host worker --listen`)
+    pub workers: Vec<String>,
+    /// `host` binary that local workers run as; `from_args` sets the running
+    /// one, since the CLI is that binary
+    pub worker_exe: Option<PathBuf>,
+    /// Deflate the receipt in the written proof file
+    pub compress: bool,
+    /// Registered guest version to prove with instead of the compiled-in guest
+    pub image: Option<String>,
+    /// Contest ID or nonce to bind the proof to, committed in the journal
+    pub challenge: Option<String>,
+    /// Secret salt of a route commitment in the journal (`--route-salt <hex>`)
+    pub route_salt: Option<[u8; 32]>,
+    /// X25519 public key of a judge to encrypt the route to (`--judge <hex>`)
+    pub judge_key: Option<[u8; 32]>,
+    /// Commit only whether the scores reach this, not the scores themselves
+    pub score_threshold: Option<i64>,
+    /// Facts about the final state to commit (`--disclose lv,node:17`)
+    pub disclose: Vec<Fact>,
+    /// Contest category whose rules the guest checks (`--category <id|file.json>`)
+    pub category: Option<Category>,
+    /// Route steps to make public (`--public-prefix <n>`, `--public-steps <i,j,...>`)
+    pub route_steps: Option<StepSelection>,
+}
+
+impl Default for ProveOptions {
+    fn default() -> Self {
+        Self {
+            receipt_kind: ReceiptKind::Succinct,
+            segment_po2: DEFAULT_SEGMENT_PO2,
+            threads: None,
+            progress: true,
+            work_dir: None,
+            local_workers: 0,
+            workers: Vec::new(),
+            worker_exe: None,
+            compress: false,
+            image: None,
+            challenge: None,
+            route_salt: None,
+            judge_key: None,
+            score_threshold: None,
+            disclose: Vec::new(),
+            category: None,
+            route_steps: None,
+        }
+    }
+}
+
+impl ProveOptions {
+    /// Profile names accepted by `--profile`
+    pub const PROFILES: &'static [&'static str] = &["default", "fast", "small-memory", "groth16"];
+
+    /// Named profile:
+    /// - `default`: succinct receipt, 2^20 cycle segments
+    /// - `fast`: composite receipt, skips the lift/join recursion (bigger proof)
+    /// - `small-memory`: succinct receipt, 2^18 cycle segments on 2 threads
+    /// - `groth16`: succinct then Groth16 compression (needs docker on x86)
+    pub fn profile(name: &str) -> Result<Self> {
+        let default = Self::default();
+        Ok(match name {
+            "default" => default,
+            "fast" => Self { receipt_kind: ReceiptKind::Composite, ..default },
+            "small-memory" => Self { segment_po2: 18, threads: Some(2), ..default },
+            "groth16" => Self { receipt_kind: ReceiptKind::Groth16, ..default },
+            _ => bail!("Unknown prove profile '{}', expected one of {:?}", name, Self::PROFILES),
+        })
+    }
+
+    /// Parse `--profile`, `--receipt`, `--segment-po2`, `--threads`, `--work-dir`,
+    /// `--workers`, `--worker`, `--image`, `--challenge`, `--route-salt`,
+    /// `--judge`, `--score-threshold`, `--disclose`, `--category`, `--public-prefix`,
+    /// `--public-steps`, `--compress` and `--no-progress`
+    ///
+    /// The profile is applied first, so explicit flags override it.
+    pub fn from_args(args: &[String]) -> Result<Self> {
+        let mut opts = match args.iter().position(|a| a == "--profile") {
+            Some(i) => Self::profile(args.get(i + 1).context("--profile requires a value")?)?,
+            None => Self::default(),
+        };
+        opts.worker_exe = std::env::current_exe().ok();
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut value = || iter.next().with_context(|| format!("{} requires a value", arg));
+            match arg.as_str() {
+                "--profile" => {
+                    value()?;
+                }
+                "--receipt" => {
+                    opts.receipt_kind = match value()?.as_str() {
+                        "composite" => ReceiptKind::Composite,
+                        "succinct" => ReceiptKind::Succinct,
+                        "groth16" => ReceiptKind::Groth16,
+                        kind => bail!("Unknown receipt kind '{}', expected composite, succinct or groth16", kind),
+                    };
+                }
+                "--segment-po2" => {
+                    opts.segment_po2 = value()?.parse().context("Invalid segment po2")?;
+                    if !(14..=22).contains(&opts.segment_po2) {
+                        bail!("Segment po2 must be within 14..=22");
+                    }
+                }
+                "--threads" => {
+                    opts.threads = Some(value()?.parse().context("Invalid thread count")?);
+                }
+                "--work-dir" => opts.work_dir = Some(PathBuf::from(value()?)),
+                "--workers" => {
+                    opts.local_workers = value()?.parse().context("Invalid worker count")?;
+                }
+                "--worker" => opts.workers.push(value()?.clone()),
+                "--image" => opts.image = Some(value()?.clone()),
+                "--challenge" => opts.challenge = Some(value()?.clone()),
+                "--route-salt" => {
+                    let salt = hex::decode(value()?).ok().and_then(|salt| salt.try_into().ok());
+                    opts.route_salt = Some(salt.context("Route salt must be 32 hex bytes")?);
+                }
+                "--judge" => {
+                    let key = hex::decode(value()?).ok().and_then(|key| key.try_into().ok());
+                    opts.judge_key = Some(key.context("Judge key must be 32 hex bytes")?);
+                }
+                "--score-threshold" => {
+                    opts.score_threshold = Some(value()?.parse().context("Invalid score threshold")?);
+                }
+                "--disclose" => {
+                    for name in value()?.split(',') {
+                        let fact = Fact::parse(name).with_context(|| format!("Unknown fact '{}' to disclose", name))?;
+                        opts.disclose.push(fact);
+                    }
+                }
+                "--category" => opts.category = Some(load_category(value()?)?),
+                "--public-prefix" => {
+                    opts.route_steps = Some(StepSelection::Prefix(value()?.parse().context("Invalid prefix length")?));
+                }
+                "--public-steps" => {
+                    let steps = value()?.split(',').map(str::parse).collect::<std::result::Result<_, _>>();
+                    opts.route_steps = Some(StepSelection::Steps(steps.context("Invalid step list")?));
+                }
+                "--compress" => opts.compress = true,
+                "--no-progress" => opts.progress = false,
+                _ => bail!("Unknown prove option '{}'", arg),
+            }
+        }
+        Ok(opts)
+    }
+
+    fn prover_opts(&self) -> ProverOpts {
+        ProverOpts::default().with_receipt_kind(self.receipt_kind)
+    }
+
+    /// Identifies a prove job, so checkpoints of another job are never reused
+    fn job_key(&self, elf: &[u8], input_digest: &[u8]) -> Result<String> {
+        let mut hasher = Sha256::new();
+        hasher.update(compute_image_id(elf)?.as_bytes());
+        hasher.update(input_digest);
+        hasher.update(format!("{:?}/{}", self.receipt_kind, self.segment_po2));
+        Ok(hex::encode(hasher.finalize()))
+    }
+}
+
+fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    format!("{}m{:02}s", secs / 60, secs % 60)
+}
+
+/// Prints steps done, elapsed time and ETA on stderr
+///
+/// Each segment costs one prove step, one lift step and (but the first) one
+/// join step; they are weighted equally, which is rough but good enough.
+struct Progress {
+    enabled: bool,
+    total: usize,
+    done: usize,
+    start: Instant,
+}
+
+impl Progress {
+    fn new(enabled: bool, total: usize) -> Self {
+        Self { enabled, total, done: 0, start: Instant::now() }
+    }
+
+    fn step(&mut self, what: &str) {
+        self.done += 1;
+        if !self.enabled {
+            return;
+        }
+        let elapsed = self.start.elapsed();
+        let eta = elapsed.mul_f64((self.total - self.done) as f64 / self.done as f64);
+        eprintln!(
+            "[prove] {} ({}/{}), elapsed {}, ETA {}",
+            what,
+            self.done,
+            self.total,
+            format_duration(elapsed),
+            format_duration(eta)
+        );
+    }
+}
+
+/// Assemble segment receipts the way `prove_session` does
+///
+/// `CompositeReceipt` is `#[non_exhaustive]` and has no constructor, so it
+/// is built through its serde form, which lists the same fields in the same
+/// order. risc0-zkvm is pinned for this; the test checks the layout.
+fn composite_receipt(segments: Vec<SegmentReceipt>) -> Result<CompositeReceipt> {
+    #[derive(serde::Serialize)]
+    struct Fields {
+        segments: Vec<SegmentReceipt>,
+        assumption_receipts: Vec<InnerAssumptionReceipt>,
+        verifier_parameters: Digest,
+    }
+    let fields = Fields {
+        segments,
+        assumption_receipts: vec![],
+        verifier_parameters: CompositeReceiptVerifierParameters::default().digest(),
+    };
+    Ok(bincode::deserialize(&bincode::serialize(&fields)?)?)
+}
+
+fn inline_asset<T: serde::Serialize>(value: &T) -> Result<Asset> {
+    Ok(Asset::Inline(Bytes::from(bincode::serialize(value)?)))
+}
+
+/// Something that can run the per-segment proving steps
+pub trait Backend {
+    fn prove_segment(&mut self, opts: &ProverOpts, segment: &Path) -> Result<SegmentReceipt>;
+    fn lift(&mut self, opts: &ProverOpts, receipt: &SegmentReceipt) -> Result<SuccinctReceipt<ReceiptClaim>>;
+    fn join(
+        &mut self,
+        opts: &ProverOpts,
+        left: &SuccinctReceipt<ReceiptClaim>,
+        right: &SuccinctReceipt<ReceiptClaim>,
+    ) -> Result<SuccinctReceipt<ReceiptClaim>>;
+}
+
+/// Backend running on the r0vm of this machine
+pub struct LocalBackend {
+    client: ApiClient,
+}
+
+impl LocalBackend {
+    pub fn new() -> Result<Self> {
+        Ok(Self { client: ApiClient::from_env()? })
+    }
+
+    pub fn prove_segment_bytes(&mut self, opts: &ProverOpts, segment: Vec<u8>) -> Result<SegmentReceipt> {
+        self.client.prove_segment(opts, Asset::Inline(Bytes::from(segment)), AssetRequest::Inline)
+    }
+}
+
+impl Backend for LocalBackend {
+    fn prove_segment(&mut self, opts: &ProverOpts, segment: &Path) -> Result<SegmentReceipt> {
+        self.client.prove_segment(opts, Asset::Path(segment.to_path_buf()), AssetRequest::Inline)
+    }
+
+    fn lift(&mut self, opts: &ProverOpts, receipt: &SegmentReceipt) -> Result<SuccinctReceipt<ReceiptClaim>> {
+        self.client.lift(opts, inline_asset(receipt)?, AssetRequest::Inline)
+    }
+
+    fn join(
+        &mut self,
+        opts: &ProverOpts,
+        left: &SuccinctReceipt<ReceiptClaim>,
+        right: &SuccinctReceipt<ReceiptClaim>,
+    ) -> Result<SuccinctReceipt<ReceiptClaim>> {
+        self.client.join(opts, inline_asset(left)?, inline_asset(right)?, AssetRequest::Inline)
+    }
+}
+
+/// Where a backend lives; opened inside the thread that uses it
+enum BackendSpec {
+    Local,
+    Remote(String),
+}
+
+impl BackendSpec {
+    fn open(&self) -> Result<Box<dyn Backend>> {
+        Ok(match self {
+            BackendSpec::Local => Box::new(LocalBackend::new()?),
+            BackendSpec::Remote(addr) => Box::new(RemoteBackend::connect(addr)?),
+        })
+    }
+}
+
+/// Run `task(backend, i)` for every `i < count`, one thread per backend
+///
+/// Results come back in index order; the first error aborts the job (the
+/// finished steps are already checkpointed).
+fn run_parallel<T, F>(specs: &[BackendSpec], count: usize, task: F) -> Result<Vec<T>>
+where
+    T: Send,
+    F: Fn(&mut dyn Backend, usize) -> Result<T> + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<T>>> = Mutex::new((0..count).map(|_| None).collect());
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = specs
+            .iter()
+            .map(|spec| {
+                scope.spawn(|| -> Result<()> {
+                    let mut backend = spec.open()?;
+                    loop {
+                        let i = next.fetch_add(1, Ordering::SeqCst);
+                        if i >= count {
+                            return Ok(());
+                        }
+                        let value = task(backend.as_mut(), i)?;
+                        results.lock().unwrap()[i] = Some(value);
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .try_for_each(|h| h.join().map_err(|_| anyhow::anyhow!("Prover thread panicked"))?)
+    })?;
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|r| r.context("Segment was not proven"))
+        .collect()
+}
+
+/// Execute the guest and prove the session according to `opts`
+///
+/// `input_digest` must cover all guest inputs; together with the image and
+/// options it decides whether checkpoints in the work dir can be reused.
+/// The work dir is removed once the receipt is complete.
+pub fn prove(
+    mut builder: ExecutorEnvBuilder<'_>,
+    elf: &[u8],
+    opts: &ProveOptions,
+    default_work_dir: PathBuf,
+    input_digest: &[u8],
+) -> Result<Receipt> {
+    let env = builder.segment_limit_po2(opts.segment_po2).build()?;
+    let prover_opts = opts.prover_opts();
+
+    // Fake receipts have no segments to drive
+    if prover_opts.dev_mode() {
+        return Ok(default_prover().prove_with_opts(env, elf, &prover_opts)?.receipt);
+    }
+
+    let work_dir = opts.work_dir.clone().unwrap_or(default_work_dir);
+    let job_key = opts.job_key(elf, input_digest)?;
+    let work = WorkDir::open(&work_dir, &job_key)?;
+
+    // Kept alive until proving is done. A thread count needs a worker even
+    // without `--workers`: r0vm only takes it from its environment.
+    let count = match opts.local_workers {
+        0 if opts.threads.is_some() && opts.workers.is_empty() => 1,
+        count => count,
+    };
+    let local_workers = LocalWorkers::spawn(opts.worker_exe.as_deref(), count, opts.threads)?;
+    let mut specs: Vec<BackendSpec> = local_workers
+        .addrs
+        .iter()
+        .chain(&opts.workers)
+        .map(|addr| BackendSpec::Remote(addr.clone()))
+        .collect();
+    if specs.is_empty() {
+        specs.push(BackendSpec::Local);
+    }
+
+    let receipt = prove_segments(env, elf, opts, &prover_opts, &work, &job_key, &specs)?;
+    work.remove()?;
+    Ok(receipt)
+}
+
+/// Guest input laid out as `ExecutorEnvBuilder` writes it, so it can be
+/// sent to a worker
+#[derive(Default)]
+pub(crate) struct GuestInput(Vec<u8>);
+
+impl GuestInput {
+    pub fn write<T: serde::Serialize>(&mut self, value: &T) -> Result<&mut Self> {
+        for word in risc0_zkvm::serde::to_vec(value)? {
+            self.0.extend_from_slice(&word.to_le_bytes());
+        }
+        Ok(self)
+    }
+
+    pub fn write_slice(&mut self, bytes: &[u8]) -> &mut Self {
+        self.0.extend_from_slice(bytes);
+        self
+    }
+
+    pub fn into_env<'a>(self) -> ExecutorEnvBuilder<'a> {
+        let mut builder = ExecutorEnv::builder();
+        builder.write_slice(&self.0);
+        builder
+    }
+}
+
+/// Prove in one r0vm call, resolving `assumptions`
+///
+/// For the short programs that compose other receipts; these are not split
+/// over workers or checkpointed. A thread count still needs one local worker,
+/// for the same reason as in [`prove`].
+pub(crate) fn prove_composed(input: GuestInput, assumptions: Vec<Receipt>, elf: &[u8], opts: &ProveOptions) -> Result<Receipt> {
+    if opts.local_workers > 0 || !opts.workers.is_empty() {
+        bail!("Workers are not supported when composing proofs");
+    }
+    let prover_opts = opts.prover_opts();
+    if opts.threads.is_none() || prover_opts.dev_mode() {
+        return compose(&prover_opts, opts.segment_po2, elf, &input.0, assumptions);
+    }
+    let local_workers = LocalWorkers::spawn(opts.worker_exe.as_deref(), 1, opts.threads)?;
+    let mut worker = RemoteBackend::connect(&local_workers.addrs[0])?;
+    worker.compose(&prover_opts, opts.segment_po2, elf, input.0, assumptions)
+}
+
+/// Body of [`prove_composed`], run here or by a worker
+pub(crate) fn compose(
+    prover_opts: &ProverOpts,
+    segment_po2: u32,
+    elf: &[u8],
+    input: &[u8],
+    assumptions: Vec<Receipt>,
+) -> Result<Receipt> {
+    let mut builder = ExecutorEnv::builder();
+    builder.write_slice(input).segment_limit_po2(segment_po2);
+    for assumption in assumptions {
+        builder.add_assumption(assumption);
+    }
+    Ok(default_prover().prove_with_opts(builder.build()?, elf, prover_opts)?.receipt)
+}
+
+/// Whether `receipt` can be resolved as an assumption of another proof
+pub(crate) fn is_resolvable(receipt: &Receipt) -> bool {
+    matches!(receipt.inner, InnerReceipt::Succinct(_) | InnerReceipt::Fake(_))
+}
+
+fn prove_segments(
+    env: ExecutorEnv<'_>,
+    elf: &[u8],
+    opts: &ProveOptions,
+    prover_opts: &ProverOpts,
+    work: &WorkDir,
+    job_key: &str,
+    specs: &[BackendSpec],
+) -> Result<Receipt> {
+    let client = ApiClient::from_env()?;
+    let start = Instant::now();
+
+    let manifest = match work.valid_manifest()? {
+        Some(manifest) => {
+            eprintln!(
+                "[prove] resuming from {} ({} segment(s))",
+                work.root().display(),
+                manifest.segments.len()
+            );
+            manifest
+        }
+        None => {
+            work.reset()?;
+            let mut segments = Vec::new();
+            let session = client.execute(
+                &env,
+                Asset::Inline(Bytes::copy_from_slice(elf)),
+                AssetRequest::Path(work.segment_dir()),
+                |_info, asset| {
+                    match asset {
+                        Asset::Path(path) => segments.push(path),
+                        _ => bail!("r0vm did not write the segment to disk"),
+                    }
+                    Ok(())
+                },
+            )?;
+            let cycles = session.cycles();
+            if opts.progress {
+                eprintln!(
+                    "[prove] executed {} cycles in {} segment(s) of 2^{} in {}",
+                    cycles,
+                    segments.len(),
+                    opts.segment_po2,
+                    format_duration(start.elapsed())
+                );
+            }
+            work.store_manifest(job_key, session.journal.bytes, cycles, &segments)?
+        }
+    };
+    let segment_count = manifest.segments.len();
+
+    let steps = match opts.receipt_kind {
+        ReceiptKind::Composite => segment_count,
+        _ => segment_count * 3 - 1,
+    };
+    let progress = Mutex::new(Progress::new(opts.progress, steps));
+    let step = |what: String| progress.lock().unwrap().step(&what);
+
+    // Prove (and lift) every segment, in parallel when there are workers
+    let lift = opts.receipt_kind != ReceiptKind::Composite;
+    let proven = run_parallel(specs, segment_count, |backend, i| {
+        let path = work.root().join(&manifest.segments[i].0);
+        let (receipt, reused) =
+            work.get_or(&format!("segment-{}", i), || backend.prove_segment(prover_opts, &path))?;
+        step(format!("segment {} {}", i, if reused { "reused" } else { "proved" }));
+        if !lift {
+            return Ok((receipt, None));
+        }
+        let (lifted, reused) = work.get_or(&format!("lift-{}", i), || backend.lift(prover_opts, &receipt))?;
+        step(format!("segment {} {}", i, if reused { "lift reused" } else { "lifted" }));
+        Ok((receipt, Some(lifted)))
+    })?;
+
+    let journal = manifest.journal;
+    let receipt = if !lift {
+        let segment_receipts = proven.into_iter().map(|(receipt, _)| receipt).collect();
+        Receipt::new(InnerReceipt::Composite(composite_receipt(segment_receipts)?), journal)
+    } else {
+        let mut backend = specs[0].open()?;
+        let mut joined: Option<SuccinctReceipt<ReceiptClaim>> = None;
+        for (i, (_, lifted)) in proven.into_iter().enumerate() {
+            let lifted = lifted.context("Segment was not lifted")?;
+            joined = Some(match joined {
+                None => lifted,
+                Some(left) => {
+                    // join-i covers segments 0..=i
+                    let (right, reused) = work.get_or(&format!("join-{}", i), || {
+                        backend.join(prover_opts, &left, &lifted)
+                    })?;
+                    step(format!("segment {} {}", i, if reused { "join reused" } else { "joined" }));
+                    right
+                }
+            });
+        }
+        let succinct = joined.context("Session produced no segments")?;
+        Receipt::new(InnerReceipt::Succinct(succinct), journal)
+    };
+
+    let receipt = if opts.receipt_kind == ReceiptKind::Groth16 {
+        if opts.progress {
+            eprintln!("[prove] compressing to Groth16...");
+        }
+        client.compress(prover_opts, inline_asset(&receipt)?, AssetRequest::Inline)?
+    } else {
+        receipt
+    };
+
+    // Catch a broken pipeline or stale checkpoint here rather than in the
+    // verifier's hands, and start over next time instead of reusing it
+    let verified = receipt.verify(compute_image_id(elf)?);
+    if verified.is_err() {
+        work.reset()?;
+    }
+    verified.context("Assembled receipt failed verification, checkpoints discarded")?;
+    if opts.progress {
+        eprintln!("[prove] done in {}", format_duration(start.elapsed()));
+    }
+    Ok(receipt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn composite_receipt_layout() {
+        let receipt = composite_receipt(Vec::new()).unwrap();
+        assert!(receipt.segments.is_empty());
+        assert!(receipt.assumption_receipts.is_empty());
+        assert_eq!(receipt.verifier_parameters, CompositeReceiptVerifierParameters::default().digest());
+        // Nothing left over or missing: the fields are exactly these three
+        let bytes = bincode::serialize(&receipt).unwrap();
+        assert_eq!(bytes.len(), 8 + 8 + 32);
+        assert_eq!(&bytes[16..], receipt.verifier_parameters.as_bytes());
+    }
+}
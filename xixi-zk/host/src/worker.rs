@@ -0,0 +1,364 @@
+//! Segment proving workers for distributed `prove`
+//!
+//! A worker (`host worker --listen <addr>`) accepts one coordinator at a
+//! time and runs segment proving, lift and join requests through its local
+//! r0vm. Addresses are `host:port` for TCP or `unix:<path>` for a Unix socket.
+//!
+//! Messages are bincode, prefixed with their length as a little-endian u32.
+//! Segments are sent by value, so workers on other machines need no shared
+//! filesystem.
+//!
+//! Links are neither authenticated nor encrypted: anyone who can reach a
+//! worker can use it, and segments (which hold the route) travel in the
+//! clear. Only listen on trusted networks or Unix sockets, or tunnel the
+//! connection, e.g. over SSH. A lying worker cannot get a wrong proof
+//! accepted, as the assembled receipt is verified before it is written.
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+use rand_core::{OsRng, RngCore};
+use risc0_zkvm::{ProverOpts, Receipt, ReceiptClaim, SegmentReceipt, SuccinctReceipt};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::prover::{self, Backend, LocalBackend};
+
+/// Upper bound of a request, above a 2^22 segment or the assumptions of a
+/// large leaderboard
+const MAX_REQUEST_LEN: usize = 256 << 20;
+
+/// Upper bound of a response: a receipt
+const MAX_RESPONSE_LEN: usize = 16 << 20;
+
+#[derive(Serialize, Deserialize)]
+enum Request {
+    ProveSegment { opts: ProverOpts, segment: Vec<u8> },
+    Lift { opts: ProverOpts, receipt: Box<SegmentReceipt> },
+    Join {
+        opts: ProverOpts,
+        left: Box<SuccinctReceipt<ReceiptClaim>>,
+        right: Box<SuccinctReceipt<ReceiptClaim>>,
+    },
+    /// A whole proof that composes `assumptions`, see `prover::compose`
+    Compose {
+        opts: ProverOpts,
+        segment_po2: u32,
+        elf: Vec<u8>,
+        input: Vec<u8>,
+        assumptions: Vec<Receipt>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+enum Response {
+    Segment(Box<SegmentReceipt>),
+    Succinct(Box<SuccinctReceipt<ReceiptClaim>>),
+    Receipt(Box<Receipt>),
+    Error(String),
+}
+
+trait Stream: Read + Write + Send {}
+impl<T: Read + Write + Send> Stream for T {}
+
+fn send<T: Serialize>(stream: &mut dyn Stream, value: &T) -> Result<()> {
+    let bytes = bincode::serialize(value)?;
+    stream.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    stream.write_all(&bytes)?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// Receive one message of at most `limit` bytes, or `None` when the peer
+/// closed the connection
+fn recv<T: DeserializeOwned>(stream: &mut dyn Stream, limit: usize) -> Result<Option<T>> {
+    let mut len = [0u8; 4];
+    match stream.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let len = u32::from_le_bytes(len) as usize;
+    if len > limit {
+        bail!("Message of {} bytes exceeds size limit", len);
+    }
+    // Grows as bytes arrive rather than trusting the announced length
+    let mut bytes = Vec::new();
+    stream.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        bail!("Connection closed in the middle of a message");
+    }
+    Ok(Some(bincode::deserialize(&bytes)?))
+}
+
+fn connect(addr: &str) -> Result<Box<dyn Stream>> {
+    Ok(match addr.strip_prefix("unix:") {
+        Some(path) => Box::new(UnixStream::connect(path)?),
+        None => Box::new(TcpStream::connect(addr)?),
+    })
+}
+
+fn handle(backend: &mut LocalBackend, request: Request) -> Response {
+    let result = match request {
+        Request::ProveSegment { opts, segment } => {
+            backend.prove_segment_bytes(&opts, segment).map(|r| Response::Segment(Box::new(r)))
+        }
+        Request::Lift { opts, receipt } => {
+            backend.lift(&opts, &receipt).map(|r| Response::Succinct(Box::new(r)))
+        }
+        Request::Join { opts, left, right } => {
+            backend.join(&opts, &left, &right).map(|r| Response::Succinct(Box::new(r)))
+        }
+        Request::Compose { opts, segment_po2, elf, input, assumptions } => {
+            prover::compose(&opts, segment_po2, &elf, &input, assumptions).map(|r| Response::Receipt(Box::new(r)))
+        }
+    };
+    result.unwrap_or_else(|e| Response::Error(format!("{:#}", e)))
+}
+
+fn serve_connection(mut stream: Box<dyn Stream>) -> Result<()> {
+    let mut backend = LocalBackend::new()?;
+    while let Some(request) = recv::<Request>(stream.as_mut(), MAX_REQUEST_LEN)? {
+        send(stream.as_mut(), &handle(&mut backend, request))?;
+    }
+    Ok(())
+}
+
+/// Remove the socket a previous worker left at `path`, and refuse to
+/// replace anything else
+fn remove_stale_socket(path: &Path) -> Result<()> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => Ok(std::fs::remove_file(path)?),
+        Ok(_) => bail!("{} exists and is not a socket", path.display()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e).with_context(|| format!("Failed to check {}", path.display())),
+    }
+}
+
+/// Entry of `host worker --listen <addr>`
+///
+/// Prints `listening on <addr>` once bound, so a parent can use port 0.
+pub fn serve(listen: &str) -> Result<()> {
+    let mut accept: Box<dyn FnMut() -> std::io::Result<Box<dyn Stream>>> =
+        match listen.strip_prefix("unix:") {
+            Some(path) => {
+                remove_stale_socket(Path::new(path))?;
+                let listener = UnixListener::bind(path)?;
+                println!("listening on unix:{}", path);
+                Box::new(move || Ok(Box::new(listener.accept()?.0) as Box<dyn Stream>))
+            }
+            None => {
+                let listener = TcpListener::bind(listen)?;
+                println!("listening on {}", listener.local_addr()?);
+                Box::new(move || Ok(Box::new(listener.accept()?.0) as Box<dyn Stream>))
+            }
+        };
+    std::io::stdout().flush()?;
+
+    // One coordinator at a time: proving already uses every core
+    loop {
+        let stream = accept()?;
+        if let Err(e) = serve_connection(stream) {
+            eprintln!("[worker] connection failed: {:#}", e);
+        }
+    }
+}
+
+/// Coordinator side of a worker connection
+pub struct RemoteBackend {
+    addr: String,
+    stream: Box<dyn Stream>,
+}
+
+impl RemoteBackend {
+    pub fn connect(addr: &str) -> Result<Self> {
+        let stream = connect(addr).with_context(|| format!("Failed to connect to worker {}", addr))?;
+        Ok(Self { addr: addr.to_string(), stream })
+    }
+
+    fn call(&mut self, request: &Request) -> Result<Response> {
+        send(self.stream.as_mut(), request)?;
+        match recv(self.stream.as_mut(), MAX_RESPONSE_LEN)? {
+            Some(Response::Error(e)) => bail!("Worker {} failed: {}", self.addr, e),
+            Some(response) => Ok(response),
+            None => bail!("Worker {} closed the connection", self.addr),
+        }
+    }
+
+    /// Run `prover::compose` on the worker
+    pub(crate) fn compose(
+        &mut self,
+        opts: &ProverOpts,
+        segment_po2: u32,
+        elf: &[u8],
+        input: Vec<u8>,
+        assumptions: Vec<Receipt>,
+    ) -> Result<Receipt> {
+        let request = Request::Compose { opts: opts.clone(), segment_po2, elf: elf.to_vec(), input, assumptions };
+        match self.call(&request)? {
+            Response::Receipt(receipt) => Ok(*receipt),
+            _ => bail!("Worker {} sent an unexpected reply", self.addr),
+        }
+    }
+}
+
+impl Backend for RemoteBackend {
+    fn prove_segment(&mut self, opts: &ProverOpts, segment: &Path) -> Result<SegmentReceipt> {
+        let segment = std::fs::read(segment)?;
+        match self.call(&Request::ProveSegment { opts: opts.clone(), segment })? {
+            Response::Segment(receipt) => Ok(*receipt),
+            _ => bail!("Worker {} sent an unexpected reply", self.addr),
+        }
+    }
+
+    fn lift(&mut self, opts: &ProverOpts, receipt: &SegmentReceipt) -> Result<SuccinctReceipt<ReceiptClaim>> {
+        match self.call(&Request::Lift { opts: opts.clone(), receipt: Box::new(receipt.clone()) })? {
+            Response::Succinct(receipt) => Ok(*receipt),
+            _ => bail!("Worker {} sent an unexpected reply", self.addr),
+        }
+    }
+
+    fn join(
+        &mut self,
+        opts: &ProverOpts,
+        left: &SuccinctReceipt<ReceiptClaim>,
+        right: &SuccinctReceipt<ReceiptClaim>,
+    ) -> Result<SuccinctReceipt<ReceiptClaim>> {
+        let request = Request::Join {
+            opts: opts.clone(),
+            left: Box::new(left.clone()),
+            right: Box::new(right.clone()),
+        };
+        match self.call(&request)? {
+            Response::Succinct(receipt) => Ok(*receipt),
+            _ => bail!("Worker {} sent an unexpected reply", self.addr),
+        }
+    }
+}
+
+/// Worker processes spawned on this machine for `--workers N` or
+/// `--threads N`, killed on drop
+///
+/// They listen on Unix sockets in a directory only this user can enter, as
+/// workers do not authenticate their coordinator.
+pub struct LocalWorkers {
+    children: Vec<Child>,
+    dir: Option<PathBuf>,
+    pub addrs: Vec<String>,
+}
+
+impl LocalWorkers {
+    /// Start `count` workers running `exe worker`, their r0vm limited to
+    /// `threads` threads
+    pub fn spawn(exe: Option<&Path>, count: usize, threads: Option<usize>) -> Result<Self> {
+        let mut workers = Self { children: Vec::new(), dir: None, addrs: Vec::new() };
+        if count == 0 {
+            return Ok(workers);
+        }
+        let exe = exe.context("Local workers need ProveOptions::worker_exe, the path of the host binary")?;
+        let dir = std::env::temp_dir().join(format!("xixi-zk-workers-{:016x}", OsRng.next_u64()));
+        std::fs::DirBuilder::new()
+            .mode(0o700)
+            .create(&dir)
+            .with_context(|| format!("Failed to create worker directory {}", dir.display()))?;
+        workers.dir = Some(dir.clone());
+        for i in 0..count {
+            let listen = format!("unix:{}", dir.join(format!("worker-{}.sock", i)).display());
+            let mut command = Command::new(exe);
+            command.args(["worker", "--listen", &listen]).stdout(Stdio::piped());
+            if let Some(threads) = threads {
+                // Inherited by the worker's r0vm
+                command.env("RAYON_NUM_THREADS", threads.to_string());
+            }
+            let mut child = command.spawn().context("Failed to spawn worker process")?;
+            let mut line = String::new();
+            BufReader::new(child.stdout.take().context("Worker has no stdout")?).read_line(&mut line)?;
+            workers.children.push(child);
+            let addr = line
+                .trim()
+                .strip_prefix("listening on ")
+                .with_context(|| format!("Unexpected worker greeting '{}'", line.trim()))?;
+            workers.addrs.push(addr.to_string());
+        }
+        Ok(workers)
+    }
+}
+
+impl Drop for LocalWorkers {
+    fn drop(&mut self) {
+        for child in &mut self.children {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        if let Some(dir) = &self.dir {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let (mut a, mut b) = UnixStream::pair().unwrap();
+        let request = Request::ProveSegment { opts: ProverOpts::succinct(), segment: vec![7; 1000] };
+        send(&mut a, &request).unwrap();
+        send(&mut a, &Response::Error("no r0vm".into())).unwrap();
+        drop(a);
+
+        match recv::<Request>(&mut b, MAX_REQUEST_LEN).unwrap() {
+            Some(Request::ProveSegment { segment, .. }) => assert_eq!(segment, vec![7; 1000]),
+            _ => panic!("wrong request"),
+        }
+        match recv::<Response>(&mut b, MAX_RESPONSE_LEN).unwrap() {
+            Some(Response::Error(message)) => assert_eq!(message, "no r0vm"),
+            _ => panic!("wrong response"),
+        }
+        // The peer hung up between messages
+        assert!(recv::<Response>(&mut b, MAX_RESPONSE_LEN).unwrap().is_none());
+    }
+
+    #[test]
+    fn refuses_oversized_and_truncated() {
+        let (mut a, mut b) = UnixStream::pair().unwrap();
+        send(&mut a, &"x".repeat(100)).unwrap();
+        assert!(recv::<String>(&mut b, 50).unwrap_err().to_string().contains("size limit"));
+
+        // Announces more than it sends
+        let (mut a, mut b) = UnixStream::pair().unwrap();
+        a.write_all(&1000u32.to_le_bytes()).unwrap();
+        a.write_all(&[0; 10]).unwrap();
+        drop(a);
+        assert!(recv::<String>(&mut b, MAX_RESPONSE_LEN).is_err());
+    }
+
+    #[test]
+    fn spawn_needs_a_worker() {
+        assert!(LocalWorkers::spawn(None, 0, None).unwrap().addrs.is_empty());
+        assert!(LocalWorkers::spawn(None, 1, Some(2)).is_err());
+        // Exits without the greeting
+        assert!(LocalWorkers::spawn(Some(Path::new("/bin/true")), 1, None).is_err());
+    }
+
+    #[test]
+    fn only_replaces_sockets() {
+        let dir = std::env::temp_dir().join(format!("xixi-zk-test-socket-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("proof.bin");
+        std::fs::write(&file, b"keep").unwrap();
+        assert!(remove_stale_socket(&file).unwrap_err().to_string().contains("not a socket"));
+        assert_eq!(std::fs::read(&file).unwrap(), b"keep");
+
+        let socket = dir.join("worker.sock");
+        drop(UnixListener::bind(&socket).unwrap());
+        remove_stale_socket(&socket).unwrap();
+        assert!(!socket.exists());
+        remove_stale_socket(&socket).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
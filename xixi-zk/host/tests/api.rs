@@ -0,0 +1,336 @@
+//! Library API round trips
+//!
+//! Proving runs in dev mode (fake receipts) but still executes the guest, so
+//! these need `r0vm` on the PATH like the CLI does.
+
+use std::sync::Once;
+
+use xixi_zk::{Category, Error, Fact, GameConfig, Identity, Policy, ProofEnvelope, ProveOptions, Reason, Reveal, SigningKey, StepSelection};
+
+/// Two nodes: the start and a goal that gives 10 HP
+const TINY_CONFIG: &str = r#"{
+    "major_adj": [[1], [0]],
+    "major_minor_adj": [[], []],
+    "major_desc": [{"Delta": []}, {"Delta": [["Hp", 10]]}],
+    "minor_desc": [],
+    "enemy_data": [],
+    "init_stat": {"hp": 100, "atk": 10, "def": 10, "mdef": 0, "exp": 0, "lv": 1, "salt": 0, "big_salt": 0},
+    "levelup_desc": []
+}"#;
+
+/// Three nodes in a line, start -> 2 -> goal 1, for routes of several chunks
+const LINE_CONFIG: &str = r#"{
+    "major_adj": [[2], [2], [0, 1]],
+    "major_minor_adj": [[], [], []],
+    "major_desc": [{"Delta": []}, {"Delta": [["Hp", 10]]}, {"Delta": [["Hp", 5]]}],
+    "minor_desc": [],
+    "enemy_data": [],
+    "init_stat": {"hp": 100, "atk": 10, "def": 10, "mdef": 0, "exp": 0, "lv": 1, "salt": 0, "big_salt": 0},
+    "levelup_desc": []
+}"#;
+
+const USER_CRED: &[u8] = b"user42\n";
+
+/// Turn on dev mode once; `set_var` while other tests read the environment
+/// would race
+fn dev_mode() {
+    static DEV_MODE: Once = Once::new();
+    DEV_MODE.call_once(|| std::env::set_var("RISC0_DEV_MODE", "1"));
+}
+
+fn quiet() -> ProveOptions {
+    ProveOptions { progress: false, ..Default::default() }
+}
+
+fn config(json: &str) -> Vec<u8> {
+    xixi_zk::json_to_rkyv(json).unwrap()
+}
+
+fn route(json: &str) -> Vec<u8> {
+    xixi_zk::route_to_bytes(json.as_bytes()).unwrap()
+}
+
+/// Prove the only route of the tiny game
+fn prove_tiny_with(user_cred: &[u8], opts: &ProveOptions) -> xixi_zk::Result<ProofEnvelope> {
+    dev_mode();
+    xixi_zk::prove(&config(TINY_CONFIG), user_cred, &route("[1]"), opts)
+}
+
+fn prove_tiny() -> ProofEnvelope {
+    prove_tiny_with(USER_CRED, &quiet()).unwrap()
+}
+
+#[test]
+fn route_formats() {
+    let expected = vec![1, 0, 0, 0, 7, 0, 0, 0];
+    assert_eq!(xixi_zk::route_to_bytes(b"[1, 7]").unwrap(), expected);
+    assert_eq!(xixi_zk::route_to_bytes(b"1\n7\n").unwrap(), expected);
+    assert!(matches!(xixi_zk::route_to_bytes(b"1 x"), Err(Error::InvalidRoute(_))));
+}
+
+#[test]
+fn invalid_config() {
+    assert!(matches!(xixi_zk::json_to_rkyv("{}"), Err(Error::InvalidConfig(_))));
+}
+
+#[test]
+fn prove_and_verify() {
+    let proof = prove_tiny();
+    let claim = xixi_zk::verify(&proof, &Policy::default()).unwrap();
+    assert_eq!(claim.user_cred.as_deref().map(str::as_bytes), Some(USER_CRED));
+    assert_eq!(claim.scores, vec![110]);
+    assert_eq!(claim.guest_version, None);
+
+    // Survives the file format
+    let decoded = ProofEnvelope::from_bytes(&proof.to_bytes().unwrap()).unwrap();
+    assert_eq!(xixi_zk::verify(&decoded, &Policy::default()).unwrap(), claim);
+}
+
+#[test]
+fn policy_rejects() {
+    let mut proof = prove_tiny();
+
+    let policy = Policy { min_score: Some(111), ..Default::default() };
+    let err = xixi_zk::verify(&proof, &policy).unwrap_err();
+    assert!(matches!(err, Error::Rejected { reason: Reason::ScoreTooLow, .. }), "{}", err);
+
+    proof.user_cred = Some(b"someone else\n".to_vec());
+    let err = xixi_zk::verify(&proof, &Policy::default()).unwrap_err();
+    assert!(matches!(err, Error::Rejected { reason: Reason::InvalidProof, .. }), "{}", err);
+}
+
+#[test]
+fn chunks_join_to_same_output() {
+    dev_mode();
+    let config = config(LINE_CONFIG);
+    let opts = quiet();
+
+    let first = xixi_zk::prove_chunk(&config, USER_CRED, None, &route("[2]"), &opts).unwrap();
+    assert!(first.is_chunk());
+    // A chunk alone is not a proof of the game
+    assert!(xixi_zk::verify(&first, &Policy::default()).is_err());
+    let second = xixi_zk::prove_chunk(&config, USER_CRED, Some(&first), &route("[1]"), &opts).unwrap();
+
+    let mut chunks = [first, second];
+    let joined = xixi_zk::join_chunks(&chunks, USER_CRED, &opts).unwrap();
+    let whole = xixi_zk::prove(&config, USER_CRED, &route("[2, 1]"), &opts).unwrap();
+    let claim = xixi_zk::verify(&joined, &Policy::default()).unwrap();
+    let whole = xixi_zk::verify(&whole, &Policy::default()).unwrap();
+    assert_eq!(claim.scores, whole.scores);
+    // Chunked or not, the same route has the same nullifier
+    assert_eq!(claim.route_nullifier, whole.route_nullifier);
+    assert_eq!(claim.route_nullifier, Some(xixi_zk::route_nullifier(&config, &route("[2, 1]"))));
+    assert!(claim.game_id.is_some());
+    assert_eq!(claim.game_id, whole.game_id);
+
+    // Chunks only join for the player they were proven for
+    assert!(matches!(xixi_zk::join_chunks(&chunks, b"someone else", &opts), Err(Error::Chain(_))));
+
+    // Chunks only join in route order
+    chunks.swap(0, 1);
+    assert!(matches!(xixi_zk::join_chunks(&chunks, USER_CRED, &opts), Err(Error::Chain(_))));
+}
+
+#[test]
+fn chunks_keep_their_challenge() {
+    dev_mode();
+    let config = config(LINE_CONFIG);
+    let opts = ProveOptions { challenge: Some("cup-1".into()), ..quiet() };
+
+    let first = xixi_zk::prove_chunk(&config, USER_CRED, None, &route("[2]"), &opts).unwrap();
+    assert_eq!(first.chunk_claim().unwrap().challenge.as_deref(), Some("cup-1"));
+    // A chunk only continues one proven for the same challenge
+    let other = ProveOptions { challenge: Some("cup-2".into()), ..quiet() };
+    assert!(matches!(xixi_zk::prove_chunk(&config, USER_CRED, Some(&first), &route("[1]"), &other), Err(Error::Chain(_))));
+    let second = xixi_zk::prove_chunk(&config, USER_CRED, Some(&first), &route("[1]"), &opts).unwrap();
+
+    // The join commits the chunks' challenge and refuses another
+    let chunks = [first, second];
+    let joined = xixi_zk::join_chunks(&chunks, USER_CRED, &quiet()).unwrap();
+    let policy = Policy { challenge: Some("cup-1".into()), ..Default::default() };
+    assert_eq!(xixi_zk::verify(&joined, &policy).unwrap().challenge.as_deref(), Some("cup-1"));
+    assert!(matches!(xixi_zk::join_chunks(&chunks, USER_CRED, &other), Err(Error::Chain(_))));
+}
+
+#[test]
+fn challenge_binding() {
+    let opts = ProveOptions { challenge: Some("cup-1".into()), ..quiet() };
+    let proof = prove_tiny_with(USER_CRED, &opts).unwrap();
+
+    let policy = Policy { challenge: Some("cup-1".into()), ..Default::default() };
+    assert_eq!(xixi_zk::verify(&proof, &policy).unwrap().challenge.as_deref(), Some("cup-1"));
+
+    let policy = Policy { challenge: Some("cup-2".into()), ..Default::default() };
+    let err = xixi_zk::verify(&proof, &policy).unwrap_err();
+    assert!(matches!(err, Error::Rejected { reason: Reason::ChallengeMismatch, .. }), "{}", err);
+
+    // Unbound proofs do not pass a challenge policy
+    let err = xixi_zk::verify(&prove_tiny(), &policy).unwrap_err();
+    assert!(matches!(err, Error::Rejected { reason: Reason::ChallengeMismatch, .. }), "{}", err);
+}
+
+#[test]
+fn identity_signatures() {
+    let key = SigningKey::from_secret("ed25519", &[7; 32]).unwrap();
+    let credential = key.identity().to_credential();
+    let mut proof = prove_tiny_with(credential.as_bytes(), &quiet()).unwrap();
+
+    // An identity credential needs its signature
+    let err = xixi_zk::verify(&proof, &Policy::default()).unwrap_err();
+    assert!(matches!(err, Error::Rejected { reason: Reason::SignatureInvalid, .. }), "{}", err);
+
+    let other = SigningKey::from_secret("ed25519", &[8; 32]).unwrap();
+    assert!(xixi_zk::sign(&mut proof, &other).is_err());
+    xixi_zk::sign(&mut proof, &key).unwrap();
+    let decoded = ProofEnvelope::from_bytes(&proof.to_bytes().unwrap()).unwrap();
+    xixi_zk::verify(&decoded, &Policy::default()).unwrap();
+
+    // Plain credentials fail a policy that requires signatures
+    let mut policy = Policy::default();
+    policy.credential.require_signature = true;
+    let err = xixi_zk::verify(&prove_tiny(), &policy).unwrap_err();
+    assert!(matches!(err, Error::Rejected { reason: Reason::SignatureInvalid, .. }), "{}", err);
+}
+
+#[test]
+fn anonymous_reveal() {
+    dev_mode();
+    let reveal = xixi_zk::anonymous_reveal(USER_CRED).unwrap();
+    let proof = xixi_zk::prove_anonymous(&config(TINY_CONFIG), &reveal, &route("[1]"), &quiet()).unwrap();
+
+    // The file keeps the credential out, and the journal only has the salted hash
+    let mut proof = ProofEnvelope::from_bytes(&proof.to_bytes().unwrap()).unwrap();
+    assert_eq!(proof.user_cred, None);
+    let claim = xixi_zk::verify(&proof, &Policy::default()).unwrap();
+    assert_eq!((claim.user_cred, claim.scores), (None, vec![110]));
+
+    let mut policy = Policy::default();
+    policy.credential.max_len = Some(32);
+    let err = xixi_zk::verify(&proof, &policy).unwrap_err();
+    assert!(matches!(err, Error::Rejected { reason: Reason::CredentialRejected, .. }), "{}", err);
+
+    let forged = Reveal { usercred: "user43\n".into(), ..reveal.clone() };
+    assert!(forged.apply(&mut proof).is_err());
+    reveal.apply(&mut proof).unwrap();
+    let claim = xixi_zk::verify(&proof, &policy).unwrap();
+    assert_eq!(claim.user_cred.as_deref().map(str::as_bytes), Some(USER_CRED));
+
+    // The same reveal proves the same journal again, so a proof can resume
+    let again = xixi_zk::prove_anonymous(&config(TINY_CONFIG), &reveal, &route("[1]"), &quiet()).unwrap();
+    assert_eq!(again.receipt.journal.bytes, proof.receipt.journal.bytes);
+
+    // Leaderboards cannot rank salted credentials once per player
+    assert!(matches!(xixi_zk::aggregate(&[again], &quiet()), Err(Error::Aggregate(_))));
+
+    // An identity's signature cannot be checked while its key is withheld
+    let identity = Identity::from_credential(b"ed25519:d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a").unwrap().unwrap();
+    let reveal = xixi_zk::anonymous_reveal(identity.to_credential().as_bytes()).unwrap();
+    assert!(xixi_zk::prove_anonymous(&config(TINY_CONFIG), &reveal, &route("[1]"), &quiet()).is_err());
+}
+
+#[test]
+fn route_commitment() {
+    let salt = [5; 32];
+    let proof = prove_tiny_with(USER_CRED, &ProveOptions { route_salt: Some(salt), ..quiet() }).unwrap();
+
+    let policy = Policy::default();
+    let route = route("[1]");
+    assert!(xixi_zk::check_route(&proof, &policy, &route, &salt).unwrap());
+    assert!(!xixi_zk::check_route(&proof, &policy, &route, &[6; 32]).unwrap());
+    let other = self::route("[1, 1]");
+    assert!(!xixi_zk::check_route(&proof, &policy, &other, &salt).unwrap());
+    // No commitment unless asked for
+    assert!(!xixi_zk::check_route(&prove_tiny(), &policy, &route, &salt).unwrap());
+}
+
+#[test]
+fn route_nullifier() {
+    let copied = prove_tiny_with(b"someone else\n", &quiet()).unwrap();
+
+    // Same route, different credentials: same nullifier
+    let expected = Some(xixi_zk::route_nullifier(&config(TINY_CONFIG), &route("[1]")));
+    assert_eq!(xixi_zk::verify(&prove_tiny(), &Policy::default()).unwrap().route_nullifier, expected);
+    assert_eq!(xixi_zk::verify(&copied, &Policy::default()).unwrap().route_nullifier, expected);
+}
+
+#[test]
+fn judge_encryption() {
+    let judge_secret = [11; 32];
+    let judge_key = xixi_core::judge::public_key(&judge_secret);
+    let mut proof = prove_tiny_with(USER_CRED, &ProveOptions { judge_key: Some(judge_key), ..quiet() }).unwrap();
+
+    let policy = Policy::default();
+    assert_eq!(xixi_zk::verify(&proof, &policy).unwrap().judge_key, Some(judge_key));
+    assert_eq!(xixi_zk::judge_decrypt(&proof, &policy, &judge_secret).unwrap(), route("[1]"));
+    assert!(xixi_zk::judge_decrypt(&proof, &policy, &[12; 32]).is_err());
+
+    // The ciphertext is part of what the proof attests
+    proof.route_ciphertext[40] ^= 1;
+    let err = xixi_zk::verify(&proof, &policy).unwrap_err();
+    assert!(matches!(err, Error::Rejected { reason: Reason::InvalidProof, .. }), "{}", err);
+}
+
+#[test]
+fn score_threshold() {
+    let proof = prove_tiny_with(USER_CRED, &ProveOptions { score_threshold: Some(100), ..quiet() }).unwrap();
+
+    let claim = xixi_zk::verify(&proof, &Policy::default()).unwrap();
+    assert!(claim.scores.is_empty());
+    assert_eq!(claim.score_threshold.unwrap().reached, vec![true]);
+
+    // min_score can only rely on the threshold
+    xixi_zk::verify(&proof, &Policy { min_score: Some(100), ..Default::default() }).unwrap();
+    let err = xixi_zk::verify(&proof, &Policy { min_score: Some(101), ..Default::default() }).unwrap_err();
+    assert!(matches!(err, Error::Rejected { reason: Reason::ScoreTooLow, .. }), "{}", err);
+}
+
+#[test]
+fn disclosure() {
+    let disclose = ["hp", "nodes_visited", "all_nodes_visited", "node:1"].map(|name| Fact::parse(name).unwrap());
+    let proof = prove_tiny_with(USER_CRED, &ProveOptions { disclose: disclose.to_vec(), ..quiet() }).unwrap();
+
+    let claim = xixi_zk::verify(&proof, &Policy::default()).unwrap();
+    let values: Vec<_> = claim.disclosed.iter().map(|d| (d.fact, d.value)).collect();
+    assert_eq!(values, vec![(disclose[0], 110), (disclose[1], 1), (disclose[2], 1), (disclose[3], 1)]);
+
+    // A node outside the game is not a fact
+    assert!(prove_tiny_with(USER_CRED, &ProveOptions { disclose: vec![Fact::NodeVisited(2)], ..quiet() }).is_err());
+}
+
+#[test]
+fn categories() {
+    let proof = prove_tiny_with(USER_CRED, &ProveOptions { category: Category::builtin("100%"), ..quiet() }).unwrap();
+    assert_eq!(proof.output().unwrap().category.unwrap().id, "100%");
+
+    xixi_zk::verify(&proof, &Policy { category: Category::builtin("100%"), ..Default::default() }).unwrap();
+    let err = xixi_zk::verify(&proof, &Policy { category: Category::builtin("pacifist"), ..Default::default() }).unwrap_err();
+    assert!(matches!(err, Error::Rejected { reason: Reason::CategoryMismatch, .. }), "{}", err);
+
+    // The only route enters node 1
+    let no_goal = Category { id: "no-goal".into(), forbidden_nodes: vec![1], ..Default::default() };
+    assert!(prove_tiny_with(USER_CRED, &ProveOptions { category: Some(no_goal), ..quiet() }).is_err());
+}
+
+#[test]
+fn public_route_steps() {
+    let proof = prove_tiny_with(USER_CRED, &ProveOptions { route_steps: Some(StepSelection::Prefix(1)), ..quiet() }).unwrap();
+    let claim = xixi_zk::verify(&proof, &Policy::default()).unwrap();
+    assert_eq!(claim.route_steps.iter().map(|s| (s.step, s.node)).collect::<Vec<_>>(), vec![(0, 1)]);
+
+    // Steps past the end of the route are refused before proving
+    let opts = ProveOptions { route_steps: Some(StepSelection::Steps(vec![0, 1])), ..quiet() };
+    assert!(matches!(prove_tiny_with(USER_CRED, &opts), Err(Error::InvalidRoute(_))));
+}
+
+#[test]
+fn game_id() {
+    let game_id = xixi_zk::game_id(&GameConfig::from_json(TINY_CONFIG).unwrap());
+    assert_eq!(xixi_zk::game_id_from_rkyv(&config(TINY_CONFIG)).unwrap(), game_id);
+
+    // The guest commits the same ID, and policies accept it as the game
+    let proof = prove_tiny();
+    let claim = xixi_zk::verify(&proof, &Policy::default()).unwrap();
+    assert_eq!(claim.game_id, Some(game_id));
+    let policy = Policy { games: [(hex::encode(game_id), "tiny".into())].into(), ..Default::default() };
+    assert_eq!(xixi_zk::verify(&proof, &policy).unwrap().game_name.as_deref(), Some("tiny"));
+}
@@ -4,6 +4,15 @@ use risc0_build::{DockerOptionsBuilder, GuestOptionsBuilder};
 fn main() {
     // use docker to build the binary deterministically
     let build_mode = env::var("RISCV_BUILD_MODE").unwrap_or_else(|_| "docker".to_string());
+    // Only docker builds give the reproducible image ID (see `host image-id`)
+    let reported_mode = match build_mode.as_str() {
+        _ if env::var_os("RISC0_SKIP_BUILD").is_some() => "skipped",
+        "local" => "local",
+        _ => "docker",
+    };
+    println!("cargo:rustc-env=GUEST_BUILD_MODE={}", reported_mode);
+    println!("cargo:rerun-if-env-changed=RISCV_BUILD_MODE");
+    println!("cargo:rerun-if-env-changed=RISC0_SKIP_BUILD");
     
     let guest_options = match build_mode.as_str() {
         "local" => GuestOptionsBuilder::default()
@@ -27,7 +36,8 @@ fn main() {
         }
     };
 
+    // The aggregator lives under guest/ too, so both share the docker root
     risc0_build::embed_methods_with_options(
-        std::iter::once(("xixi_verifier", guest_options)).collect()
+        [("xixi_verifier", guest_options.clone()), ("xixi_aggregator", guest_options)].into()
     );
 }
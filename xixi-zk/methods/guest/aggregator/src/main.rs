@@ -0,0 +1,64 @@
+//! Aggregation guest: many player proofs in, one leaderboard out
+//!
+//! Reads the image ID of the player guest and the journals of N player
+//! proofs. Each journal is checked with `env::verify`, so the host must add
+//! the matching receipts as assumptions; the final receipt only holds if all
+//! of them do.
+
+use risc0_zkvm::guest::env;
+use risc0_zkvm::sha::Digest;
+use xixi_core::{Leaderboard, LeaderboardEntry, LegacyOutput, Output};
+
+fn main() {
+    let image_id: [u8; 32] = env::read();
+    let journals: Vec<Vec<u8>> = env::read();
+    assert!(!journals.is_empty(), "No proofs to aggregate");
+
+    let mut config_hash = None;
+    let mut challenge = None;
+    let mut category = None;
+    let mut entries = Vec::with_capacity(journals.len());
+    for journal in &journals {
+        env::verify(Digest::from(image_id), journal).expect("Player proof does not verify");
+        let output = if Output::is_current(journal) {
+            risc0_zkvm::serde::from_slice::<Output, _>(journal)
+        } else {
+            risc0_zkvm::serde::from_slice::<LegacyOutput, _>(journal).map(Output::from)
+        };
+        let output = output.expect("Invalid player journal");
+        assert!(output.score_threshold.is_none(), "Player proof hides its scores");
+        if let Some(chunk_image_id) = output.chunk_image_id {
+            assert_eq!(chunk_image_id, image_id, "Player proof joined from chunks of another image");
+        }
+
+        // All proofs must be for the same game, challenge and category
+        match &config_hash {
+            None => {
+                config_hash = Some(output.config_hash);
+                challenge = output.challenge;
+                category = output.category;
+            }
+            Some(hash) => {
+                assert_eq!(*hash, output.config_hash, "Proofs are for different games");
+                assert_eq!(challenge, output.challenge, "Proofs are for different challenges");
+                assert_eq!(category, output.category, "Proofs are for different categories");
+            }
+        }
+        entries.push(LeaderboardEntry {
+            user_cred_hash: output.user_cred_hash,
+            scores: output.scores,
+            route_nullifier: output.route_nullifier,
+        });
+    }
+
+    // Best scores first; a player with several proofs keeps the best one
+    Leaderboard::rank(&mut entries);
+
+    env::commit(&Leaderboard {
+        config_hash: config_hash.unwrap(),
+        image_id,
+        challenge,
+        category,
+        entries,
+    });
+}
@@ -0,0 +1,223 @@
+//! Canonical encoding of a game config
+//!
+//! The game ID is SHA-256 of this encoding. Unlike the config hash over the
+//! rkyv bytes, it does not change with the rkyv version, alignment or
+//! padding, so the same game keeps its ID across toolchains. The guest
+//! encodes the archived config it simulates; the host can encode a config
+//! parsed from JSON, and both must give the same bytes.
+//!
+//! Layout, integers little-endian, each list a `u32` length then its items:
+//! 1. `DOMAIN`
+//! 2. `major_adj`, then `major_minor_adj`: lists of lists of `u32`
+//! 3. `major_desc`: list of `0u8` + enemy index `u32`, or `1u8` + list of
+//!    (attribute `u8`, delta `i32`), attributes numbered in `AttrType` order
+//!    from `hp` = 0 to `big_salt` = 7
+//! 4. `minor_desc`: list of `atk`, `def`, `hp`, `mdef` as `i32`
+//! 5. `enemy_data`: list of `atk`, `def`, `hp`, `attimes`, `exp` as `i32`,
+//!    then `magic`, `solid`, `speedy`, `nobomb` as `u8` 0 or 1
+//! 6. `init_stat`: `hp`, `atk`, `def`, `mdef`, `exp` as `i32`, `lv` as `u32`,
+//!    `salt`, `big_salt` as `i32`
+//! 7. `levelup_desc`: list of `minor` `u32`, `need` `i32`, `clear` `u8`
+
+use rkyv::vec::ArchivedVec;
+
+use crate::*;
+
+/// First bytes of the encoding, naming its version
+pub const DOMAIN: &[u8] = b"xixi-zk game v1";
+
+impl GameConfig {
+    /// Canonical encoding, see the module docs
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        encode(self)
+    }
+}
+
+impl ArchivedGameConfig {
+    /// Canonical encoding, the same as `GameConfig::canonical_bytes` of the
+    /// config before archiving
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        encode(self)
+    }
+}
+
+/// Canonical encoding of untrusted rkyv bytes, read without deserializing;
+/// `None` if they are not a valid config
+pub fn from_rkyv(bytes: &[u8]) -> Option<Vec<u8>> {
+    let mut aligned = rkyv::AlignedVec::with_capacity(bytes.len());
+    aligned.extend_from_slice(bytes);
+    let config = rkyv::check_archived_root::<GameConfig>(&aligned).ok()?;
+    Some(config.canonical_bytes())
+}
+
+fn encode(config: &impl Canonical) -> Vec<u8> {
+    let mut out = DOMAIN.to_vec();
+    config.encode(&mut out);
+    out
+}
+
+trait Canonical {
+    fn encode(&self, out: &mut Vec<u8>);
+}
+
+impl Canonical for u32 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Canonical for i32 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Canonical for bool {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+}
+
+fn encode_list<'a, T: Canonical + 'a>(items: &'a [T], out: &mut Vec<u8>) {
+    (items.len() as u32).encode(out);
+    for item in items {
+        item.encode(out);
+    }
+}
+
+impl<T: Canonical> Canonical for Vec<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        encode_list(self, out);
+    }
+}
+
+impl<T: Canonical> Canonical for ArchivedVec<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        encode_list(self.as_slice(), out);
+    }
+}
+
+impl<A: Canonical, B: Canonical> Canonical for (A, B) {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out);
+        self.1.encode(out);
+    }
+}
+
+/// Structs encode their fields in the listed order, owned and archived alike
+macro_rules! canonical_fields {
+    ($($ty:ty { $($field:ident),* })*) => {
+        $(impl Canonical for $ty {
+            fn encode(&self, out: &mut Vec<u8>) {
+                $(self.$field.encode(out);)*
+            }
+        })*
+    };
+}
+
+canonical_fields! {
+    GameConfig { major_adj, major_minor_adj, major_desc, minor_desc, enemy_data, init_stat, levelup_desc }
+    ArchivedGameConfig { major_adj, major_minor_adj, major_desc, minor_desc, enemy_data, init_stat, levelup_desc }
+    MinorDesc { atk, def, hp, mdef }
+    ArchivedMinorDesc { atk, def, hp, mdef }
+    Enemy { atk, def, hp, attimes, exp, magic, solid, speedy, nobomb }
+    ArchivedEnemy { atk, def, hp, attimes, exp, magic, solid, speedy, nobomb }
+    PlayerState { hp, atk, def, mdef, exp, lv, salt, big_salt }
+    ArchivedPlayerState { hp, atk, def, mdef, exp, lv, salt, big_salt }
+    LevelUp { minor, need, clear }
+    ArchivedLevelUp { minor, need, clear }
+}
+
+impl Canonical for MajorDesc {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            MajorDesc::Enemy(enemy) => {
+                out.push(0);
+                enemy.encode(out);
+            }
+            MajorDesc::Delta(attrs) => {
+                out.push(1);
+                attrs.encode(out);
+            }
+        }
+    }
+}
+
+impl Canonical for ArchivedMajorDesc {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            ArchivedMajorDesc::Enemy(enemy) => {
+                out.push(0);
+                enemy.encode(out);
+            }
+            ArchivedMajorDesc::Delta(attrs) => {
+                out.push(1);
+                attrs.encode(out);
+            }
+        }
+    }
+}
+
+impl Canonical for AttrType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+}
+
+impl Canonical for ArchivedAttrType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let attr = match self {
+            ArchivedAttrType::Hp => AttrType::Hp,
+            ArchivedAttrType::Atk => AttrType::Atk,
+            ArchivedAttrType::Def => AttrType::Def,
+            ArchivedAttrType::Mdef => AttrType::Mdef,
+            ArchivedAttrType::Exp => AttrType::Exp,
+            ArchivedAttrType::Lv => AttrType::Lv,
+            ArchivedAttrType::Salt => AttrType::Salt,
+            ArchivedAttrType::BigSalt => AttrType::BigSalt,
+        };
+        attr.encode(out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One of everything: both node kinds, a minor node, an enemy with
+    /// flags and a level
+    const CONFIG: &str = r#"{
+        "major_adj": [[1, 2], [], [1]],
+        "major_minor_adj": [[0], [], []],
+        "major_desc": [{"Delta": []}, {"Delta": [["Hp", 10], ["BigSalt", -1]]}, {"Enemy": 0}],
+        "minor_desc": [{"atk": 1, "def": 2, "hp": 3, "mdef": 4}],
+        "enemy_data": [{"atk": 5, "def": 6, "hp": 7, "attimes": 1, "exp": 8,
+                        "magic": true, "solid": false, "speedy": true, "nobomb": false}],
+        "init_stat": {"hp": 100, "atk": 10, "def": 10, "mdef": 0, "exp": 0, "lv": 1, "salt": 0, "big_salt": 0},
+        "levelup_desc": [{"minor": 0, "need": 9, "clear": true}]
+    }"#;
+
+    /// Encoding of `CONFIG`, by section of the layout. Game IDs change
+    /// whenever this does, so it must not.
+    const GOLDEN: &str = concat!(
+        "786978692d7a6b2067616d65207631",
+        "03000000020000000100000002000000000000000100000001000000",
+        "0300000001000000000000000000000000000000",
+        "0300000001000000000102000000000a00000007ffffffff0000000000",
+        "0100000001000000020000000300000004000000",
+        "01000000050000000600000007000000010000000800000001000100",
+        "640000000a0000000a0000000000000000000000010000000000000000000000",
+        "01000000000000000900000001",
+    );
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn golden() {
+        let config = GameConfig::from_json(CONFIG).unwrap();
+        assert_eq!(hex(&config.canonical_bytes()), GOLDEN);
+        assert_eq!(from_rkyv(&config.to_rkyv()).unwrap(), config.canonical_bytes());
+    }
+}
@@ -0,0 +1,96 @@
+//! Route encryption to a judge
+//!
+//! The route is sealed to the judge's X25519 key: X25519 between a sender
+//! key and the judge key, HKDF-SHA256 over the shared secret and both public
+//! keys for the XChaCha20-Poly1305 key. The nonce is taken from the sender
+//! public key and the config hash is authenticated along.
+//!
+//! The host derives the sender secret from the judge key, config hash and
+//! route (so an interrupted proof resumes), not from randomness: the same
+//! route sealed to the same judge always gives the same ciphertext. That
+//! links proofs of one route, as their route nullifier already does.
+//!
+//! Ciphertext layout: sender public key (32 bytes), then the sealed
+//! route bytes with their 16-byte tag.
+
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use hkdf::Hkdf;
+use sha2::Sha256;
+use x25519_dalek::{PublicKey, SharedSecret, StaticSecret};
+
+/// HKDF info prefix, followed by the sender and judge public keys
+const KEY_INFO: &[u8] = b"xixi-zk judge key";
+
+pub const SENDER_KEY_LEN: usize = 32;
+pub const TAG_LEN: usize = 16;
+
+/// Public key of an X25519 secret
+pub fn public_key(secret: &[u8; 32]) -> [u8; 32] {
+    PublicKey::from(&StaticSecret::from(*secret)).to_bytes()
+}
+
+/// Cipher for the X25519 output `shared` of `sender_key` and `judge_key`
+fn cipher(shared: SharedSecret, sender_key: &[u8; 32], judge_key: &[u8; 32]) -> Option<XChaCha20Poly1305> {
+    // A low-order key would give a shared secret anyone knows
+    if !shared.was_contributory() {
+        return None;
+    }
+    let mut key = [0; 32];
+    Hkdf::<Sha256>::new(None, shared.as_bytes())
+        .expand_multi_info(&[KEY_INFO, sender_key, judge_key], &mut key)
+        .ok()?;
+    Some(XChaCha20Poly1305::new(&key.into()))
+}
+
+/// Seal `route_bytes` of the game `config_hash` to `judge_key`; `None` if
+/// the judge key is not usable
+pub fn encrypt(judge_key: &[u8; 32], sender_secret: &[u8; 32], config_hash: &[u8; 32], route_bytes: &[u8]) -> Option<Vec<u8>> {
+    let secret = StaticSecret::from(*sender_secret);
+    let sender_key = PublicKey::from(&secret).to_bytes();
+    let cipher = cipher(secret.diffie_hellman(&PublicKey::from(*judge_key)), &sender_key, judge_key)?;
+    let nonce = XNonce::from_slice(&sender_key[..24]);
+    let sealed = cipher.encrypt(nonce, Payload { msg: route_bytes, aad: config_hash }).ok()?;
+
+    let mut ciphertext = Vec::with_capacity(SENDER_KEY_LEN + sealed.len());
+    ciphertext.extend_from_slice(&sender_key);
+    ciphertext.extend_from_slice(&sealed);
+    Some(ciphertext)
+}
+
+/// Open a ciphertext made by [`encrypt`] with the judge's secret key
+pub fn decrypt(judge_secret: &[u8; 32], config_hash: &[u8; 32], ciphertext: &[u8]) -> Option<Vec<u8>> {
+    if ciphertext.len() < SENDER_KEY_LEN + TAG_LEN {
+        return None;
+    }
+    let (sender_key, sealed) = ciphertext.split_at(SENDER_KEY_LEN);
+    let sender_key: [u8; 32] = sender_key.try_into().ok()?;
+    let shared = StaticSecret::from(*judge_secret).diffie_hellman(&PublicKey::from(sender_key));
+    let cipher = cipher(shared, &sender_key, &public_key(judge_secret))?;
+    let nonce = XNonce::from_slice(&sender_key[..24]);
+    cipher.decrypt(nonce, Payload { msg: sealed, aad: config_hash }).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sealed_to_the_judge_only() {
+        let (judge_secret, sender_secret) = ([1; 32], [2; 32]);
+        let judge_key = public_key(&judge_secret);
+        let ciphertext = encrypt(&judge_key, &sender_secret, &[3; 32], b"route").unwrap();
+        assert_eq!(decrypt(&judge_secret, &[3; 32], &ciphertext).unwrap(), b"route");
+        assert!(decrypt(&[4; 32], &[3; 32], &ciphertext).is_none());
+        assert!(decrypt(&judge_secret, &[5; 32], &ciphertext).is_none());
+
+        // Not keyed with the raw X25519 output
+        let shared = StaticSecret::from(judge_secret).diffie_hellman(&PublicKey::from(public_key(&sender_secret)));
+        let raw = XChaCha20Poly1305::new(shared.as_bytes().into());
+        let nonce = XNonce::from_slice(&ciphertext[..24]);
+        assert!(raw.decrypt(nonce, Payload { msg: &ciphertext[SENDER_KEY_LEN..], aad: &[3; 32] }).is_err());
+
+        // A low-order judge key is refused
+        assert!(encrypt(&[0; 32], &sender_secret, &[3; 32], b"route").is_none());
+    }
+}
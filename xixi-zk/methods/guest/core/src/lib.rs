@@ -1,6 +1,10 @@
 use serde::{Serialize, Deserialize};
 use rkyv::{Archive, Serialize as RkyvSerialize, Deserialize as RkyvDeserialize};
 
+pub mod canonical;
+#[cfg(feature = "judge")]
+pub mod judge;
+
 /// Game configuration data structure (matches convert_motadata.py)
 #[derive(Debug, Serialize, Deserialize, Archive, RkyvSerialize, RkyvDeserialize)]
 #[archive(check_bytes)]
@@ -25,7 +29,7 @@ pub enum MajorDesc {
 }
 
 /// Attribute type enum
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Archive, RkyvSerialize, RkyvDeserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Archive, RkyvSerialize, RkyvDeserialize)]
 #[archive(check_bytes)]
 #[archive_attr(derive(Debug))]
 pub enum AttrType {
@@ -148,12 +152,477 @@ impl GameConfig {
         rkyv::from_bytes(bytes)
             .expect("Failed to deserialize GameConfig")
     }
+
+    /// Deserialize untrusted rkyv bytes, `None` if they are not a valid config
+    ///
+    /// Copies into an aligned buffer first, so any byte slice is accepted.
+    pub fn try_from_rkyv(bytes: &[u8]) -> Option<Self> {
+        let mut aligned = rkyv::AlignedVec::with_capacity(bytes.len());
+        aligned.extend_from_slice(bytes);
+        rkyv::from_bytes(&aligned).ok()
+    }
 }
 
 /// Output structure containing all verification data
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Output {
+    /// Always `OUTPUT_VERSION`
+    pub version: u32,
+    pub config_hash: [u8; 32],
+    pub user_cred_hash: [u8; 32],
+    /// Empty when only a threshold is shown (`score_threshold`)
+    pub scores: Vec<i64>,
+    /// Set when the route was proven in chunks and joined: the image ID the
+    /// chunks were verified against. Verifiers must check it is the image
+    /// ID of this very proof.
+    pub chunk_image_id: Option<[u8; 32]>,
+    /// Contest ID or nonce the prover was asked to bind the proof to
+    pub challenge: Option<String>,
+    /// SHA-256 of a prover-chosen salt followed by the route bytes, when
+    /// asked for; lets the player show later which route was proven
+    pub route_commitment: Option<[u8; 32]>,
+    /// Same for every proof of this route in this game, whoever proves it
+    /// (see [`NULLIFIER_DOMAIN`]), chunked or not
+    pub route_nullifier: Option<[u8; 32]>,
+    /// Route encrypted to a judge, shipped in the proof file
+    pub route_ciphertext: Option<RouteCiphertext>,
+    /// Which scores reach a public threshold, in place of the scores
+    pub score_threshold: Option<ScoreThreshold>,
+    /// Facts about the final state the prover chose to make public
+    pub disclosed: Vec<Disclosure>,
+    /// Contest category whose rules the route follows
+    pub category: Option<Category>,
+    /// Route steps the prover chose to make public, in route order
+    pub route_steps: Vec<RouteStep>,
+    /// SHA-256 of the canonical config encoding (see [`canonical`]), which
+    /// unlike `config_hash` does not depend on rkyv; `None` for guest 0.1.0
+    pub game_id: Option<[u8; 32]>,
+}
+
+/// Something about the final game state `prove --disclose` can make public
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Fact {
+    /// Final value of a player stat
+    Stat(AttrType),
+    /// Number of enemy nodes completed
+    EnemiesDefeated,
+    /// Number of major nodes completed, not counting the start
+    NodesVisited,
+    /// Whether every major node was completed
+    AllNodesVisited,
+    /// Whether this major node was completed
+    NodeVisited(u32),
+}
+
+/// Longest accepted disclosure list
+pub const MAX_DISCLOSED: usize = 64;
+
+impl Fact {
+    const STATS: [(&'static str, AttrType); 8] = [
+        ("hp", AttrType::Hp),
+        ("atk", AttrType::Atk),
+        ("def", AttrType::Def),
+        ("mdef", AttrType::Mdef),
+        ("exp", AttrType::Exp),
+        ("lv", AttrType::Lv),
+        ("salt", AttrType::Salt),
+        ("big_salt", AttrType::BigSalt),
+    ];
+
+    /// Parse a fact name as `name` prints it, e.g. `lv` or `node:17`
+    pub fn parse(name: &str) -> Option<Self> {
+        if let Some(node) = name.strip_prefix("node:") {
+            return node.parse().ok().map(Fact::NodeVisited);
+        }
+        match name {
+            "enemies_defeated" => Some(Fact::EnemiesDefeated),
+            "nodes_visited" => Some(Fact::NodesVisited),
+            "all_nodes_visited" => Some(Fact::AllNodesVisited),
+            _ => Self::STATS.iter().find(|(n, _)| *n == name).map(|&(_, attr)| Fact::Stat(attr)),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Fact::Stat(attr) => Self::STATS.iter().find(|(_, a)| a == attr).map_or("?", |(n, _)| n).to_string(),
+            Fact::EnemiesDefeated => "enemies_defeated".into(),
+            Fact::NodesVisited => "nodes_visited".into(),
+            Fact::AllNodesVisited => "all_nodes_visited".into(),
+            Fact::NodeVisited(node) => format!("node:{}", node),
+        }
+    }
+
+    /// Whether the value is a yes/no answer (`0` or `1`)
+    pub fn is_flag(&self) -> bool {
+        matches!(self, Fact::AllNodesVisited | Fact::NodeVisited(_))
+    }
+}
+
+/// A disclosed fact and its value
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Disclosure {
+    pub fact: Fact,
+    /// Flags are `0` or `1`
+    pub value: i64,
+}
+
+/// Which route steps `prove --public-prefix` or `--public-steps` makes public
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StepSelection {
+    /// The first n steps
+    Prefix(u32),
+    /// These step positions, strictly increasing
+    Steps(Vec<u32>),
+}
+
+/// A public route step: the node at a position of the route
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RouteStep {
+    /// Position in the route, from 0
+    pub step: u32,
+    pub node: u32,
+}
+
+impl StepSelection {
+    /// Selected steps of a route in its byte form (`u32` little-endian per
+    /// node), `None` when the selection does not fit the route
+    pub fn select(&self, route_bytes: &[u8]) -> Option<Vec<RouteStep>> {
+        let len = route_bytes.len() / 4;
+        let step = |step: u32| {
+            let at = step as usize * 4;
+            let node = u32::from_le_bytes(route_bytes.get(at..at + 4)?.try_into().ok()?);
+            Some(RouteStep { step, node })
+        };
+        match self {
+            StepSelection::Prefix(n) if *n as usize <= len => (0..*n).map(step).collect(),
+            StepSelection::Steps(steps) if steps.windows(2).all(|w| w[0] < w[1]) => {
+                steps.iter().map(|&s| step(s)).collect()
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Contest category: rules the whole route must follow on top of finishing
+/// the game, committed with its ID so verifiers see exactly what was checked
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Category {
+    /// e.g. `any%` or `100%` (at most `MAX_CATEGORY_ID_LEN` bytes)
+    pub id: String,
+    /// Every major node completed
+    pub all_nodes: bool,
+    /// Every enemy node defeated
+    pub all_enemies: bool,
+    /// Level never above the starting level after any node
+    pub no_level_up: bool,
+    /// No enemy node fought
+    pub pacifist: bool,
+    /// Major nodes the route must not enter
+    pub forbidden_nodes: Vec<u32>,
+}
+
+/// Longest accepted category ID, in bytes
+pub const MAX_CATEGORY_ID_LEN: usize = 64;
+
+impl Category {
+    /// IDs of the predefined categories
+    pub const BUILTIN: &'static [&'static str] = &["any%", "100%", "all-enemies", "no-level-up", "pacifist"];
+
+    /// Predefined category with this ID
+    pub fn builtin(id: &str) -> Option<Self> {
+        let rules = match id {
+            "any%" => Self::default(),
+            "100%" => Self { all_nodes: true, ..Self::default() },
+            "all-enemies" => Self { all_enemies: true, ..Self::default() },
+            "no-level-up" => Self { no_level_up: true, ..Self::default() },
+            "pacifist" => Self { pacifist: true, ..Self::default() },
+            _ => return None,
+        };
+        Some(Self { id: id.to_string(), ..rules })
+    }
+}
+
+/// Score components compared with a public threshold, hiding their values
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScoreThreshold {
+    pub threshold: i64,
+    /// Per score component: at least `threshold`
+    pub reached: Vec<bool>,
+}
+
+impl ScoreThreshold {
+    pub fn new(threshold: i64, scores: &[i64]) -> Self {
+        Self { threshold, reached: scores.iter().map(|&score| score >= threshold).collect() }
+    }
+}
+
+/// Judge a route was encrypted to, and SHA-256 of the ciphertext (see
+/// `judge`); the proof attests it decrypts to the proven route
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RouteCiphertext {
+    pub judge_key: [u8; 32],
+    pub ciphertext_hash: [u8; 32],
+}
+
+/// Route encryption inputs: the judge's X25519 public key and the prover's
+/// sender secret, derived from the route (see `judge`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JudgeInput {
+    pub judge_key: [u8; 32],
+    pub sender_secret: [u8; 32],
+}
+
+/// What the prover asks the guest to add to the output, sent after the
+/// chained proof mode
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OutputOptions {
+    /// Contest ID or nonce to bind the proof to
+    pub challenge: Option<String>,
+    /// Salt of the route commitment
+    pub route_salt: Option<[u8; 32]>,
+    pub judge: Option<JudgeInput>,
+    /// Commit only whether each score reaches this
+    pub score_threshold: Option<i64>,
+    /// Facts about the final state to commit (at most `MAX_DISCLOSED`)
+    pub disclose: Vec<Fact>,
+    /// Category whose rules the guest checks
+    pub category: Option<Category>,
+    /// Route steps to commit
+    pub route_steps: Option<StepSelection>,
+}
+
+/// Route nullifier domain. The nullifier starts as SHA-256 of this domain
+/// and the config hash, then each route node `n` turns it into SHA-256 of
+/// itself and `n` as `u32` little-endian. Node by node, it comes out the same
+/// whether the route is proven whole or in chunks. Two proofs with the same
+/// nullifier used the identical route.
+pub const NULLIFIER_DOMAIN: &[u8] = b"xixi-zk route nullifier v2";
+
+/// Longest accepted challenge, in bytes
+pub const MAX_CHALLENGE_LEN: usize = 256;
+
+/// First journal word of an [`Output`], naming its layout
+///
+/// The low byte counts layouts (1 was [`LegacyOutput`]), the tag above it
+/// keeps the word above any byte value, so it never reads as the first config
+/// hash byte of a `LegacyOutput` nor equals `CHUNK_TAG`.
+pub const OUTPUT_VERSION: u32 = u32::from_le_bytes([2, b'O', b'U', b'T']);
+
+impl Output {
+    /// Whether a journal starts with `OUTPUT_VERSION`; other player journals
+    /// are `LegacyOutput`s when their first word is a byte value
+    pub fn is_current(journal: &[u8]) -> bool {
+        journal.get(..4) == Some(&OUTPUT_VERSION.to_le_bytes()[..])
+    }
+}
+
+/// Journal layout of guest 0.1.0, before chained proofs
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LegacyOutput {
     pub config_hash: [u8; 32],
     pub user_cred_hash: [u8; 32],
     pub scores: Vec<i64>,
-}
\ No newline at end of file
+}
+
+impl From<LegacyOutput> for Output {
+    fn from(legacy: LegacyOutput) -> Self {
+        Self {
+            version: OUTPUT_VERSION,
+            config_hash: legacy.config_hash,
+            user_cred_hash: legacy.user_cred_hash,
+            scores: legacy.scores,
+            chunk_image_id: None,
+            challenge: None,
+            route_commitment: None,
+            route_nullifier: None,
+            route_ciphertext: None,
+            score_threshold: None,
+            disclosed: Vec::new(),
+            category: None,
+            route_steps: Vec::new(),
+            game_id: None,
+        }
+    }
+}
+
+/// Game progress between route chunks: player stats and which major and
+/// minor nodes are completed (bitmaps, 32 nodes per word)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainState {
+    pub player: PlayerState,
+    pub majors: Vec<u32>,
+    pub minors: Vec<u32>,
+}
+
+impl ChainState {
+    /// Whether major node `node` is completed
+    pub fn major_completed(&self, node: usize) -> bool {
+        self.majors.get(node / 32).is_some_and(|word| word >> (node % 32) & 1 == 1)
+    }
+
+    /// Commitment of the state in `config_hash`, as chained between chunks
+    ///
+    /// The bytes returned are hashed with SHA-256 by guest and host.
+    pub fn commitment_bytes(&self, config_hash: &[u8; 32]) -> Vec<u8> {
+        let p = &self.player;
+        let mut bytes = Vec::with_capacity(32 + 40 + 4 * (self.majors.len() + self.minors.len()));
+        bytes.extend_from_slice(config_hash);
+        for v in [p.hp, p.atk, p.def, p.mdef, p.exp, p.lv as i32, p.salt, p.big_salt] {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+        for bitmap in [&self.majors, &self.minors] {
+            bytes.extend_from_slice(&(bitmap.len() as u32).to_le_bytes());
+            for word in bitmap {
+                bytes.extend_from_slice(&word.to_le_bytes());
+            }
+        }
+        bytes
+    }
+}
+
+/// State commitment standing for the initial state of the game
+pub const GENESIS_STATE: [u8; 32] = [0; 32];
+
+/// First journal word of a chunk proof
+///
+/// Journals are told apart by their first word: `CHUNK_TAG` for a chunk,
+/// `OUTPUT_VERSION` for an [`Output`], and a byte value (the first config
+/// hash byte) for a [`LegacyOutput`]. Being neither of the others, a chunk
+/// journal never decodes as a game output and cannot pass as a full proof.
+pub const CHUNK_TAG: u32 = u32::from_le_bytes(*b"CHNK");
+
+/// Journal of a route chunk proof: from state `state_in`, the chunk leads to
+/// `state_out`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkClaim {
+    /// Always `CHUNK_TAG`
+    pub tag: u32,
+    pub config_hash: [u8; 32],
+    /// Game ID of the config, as in [`Output`]
+    pub game_id: [u8; 32],
+    /// Commitment of the state before the chunk, `GENESIS_STATE` for the first
+    pub state_in: [u8; 32],
+    pub state_out: ChainState,
+    /// Credential hash of the player the chunk is proven for; every chunk of
+    /// a join must have the joining player's
+    pub user_cred_hash: [u8; 32],
+    /// Challenge the chunk is proven for; every chunk of a join must have
+    /// the same, which the joined proof commits
+    pub challenge: Option<String>,
+    /// Route nullifier so far, before and after the chunk's nodes
+    pub route_in: [u8; 32],
+    pub route_out: [u8; 32],
+    /// Last node of the chunk; the final chunk must end with node 1
+    pub last_node: u32,
+}
+
+/// Where a route chunk starts: the state and route nullifier the chunk
+/// before it ended with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainStart {
+    pub state: ChainState,
+    pub route: [u8; 32],
+}
+
+/// Guest mode, sent after the profiling switch and before the `OutputOptions`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ChainInput {
+    /// Prove the whole route (the usual proof)
+    Off,
+    /// Prove one route chunk from where the chunk before it ended (`None`:
+    /// start of the game)
+    Step(Option<ChainStart>),
+    /// Join chunk proofs, given as their journals, into the final `Output`;
+    /// the chunk receipts are verified against `image_id`
+    Join { image_id: [u8; 32], journals: Vec<Vec<u8>> },
+}
+
+/// One player in a [`Leaderboard`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    pub user_cred_hash: [u8; 32],
+    pub scores: Vec<i64>,
+    pub route_nullifier: Option<[u8; 32]>,
+}
+
+/// Journal of the aggregation guest
+///
+/// Starts with the config hash like [`Output`] after its version, so proof
+/// files can show the game of either. Entries are sorted best first, one per credential.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Leaderboard {
+    pub config_hash: [u8; 32],
+    /// Image ID of the guest that proved every entry
+    pub image_id: [u8; 32],
+    /// Challenge shared by every entry
+    pub challenge: Option<String>,
+    /// Category shared by every entry
+    pub category: Option<Category>,
+    pub entries: Vec<LeaderboardEntry>,
+}
+
+impl Leaderboard {
+    /// Sort best scores first, keeping only the best entry of each credential
+    pub fn rank(entries: &mut Vec<LeaderboardEntry>) {
+        entries.sort_by(|a, b| b.scores.cmp(&a.scores).then(a.user_cred_hash.cmp(&b.user_cred_hash)));
+        let mut seen = std::collections::BTreeSet::new();
+        entries.retain(|entry| seen.insert(entry.user_cred_hash));
+    }
+}
+
+/// Host file descriptor the guest writes its `CycleProfile` to
+///
+/// Kept apart from stdout/stderr so debug prints never corrupt the profile.
+pub const PROFILE_FD: u32 = 0x7878;
+
+/// Guest profiling switch, sent after the route
+pub const PROFILE_OFF: u32 = 0;
+pub const PROFILE_PHASES: u32 = 1;
+pub const PROFILE_CHUNKS: u32 = 2;
+
+/// Number of route nodes covered by each `CycleProfile::route_chunks` entry
+pub const PROFILE_CHUNK_NODES: usize = 100;
+
+/// Cycle counts of each guest phase, reported outside of the journal
+///
+/// Counts come from `env::cycle_count` and are not checked by the circuit,
+/// so they are only meaningful for performance work.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CycleProfile {
+    pub read_input: u64,
+    pub hashing: u64,
+    pub parse_route: u64,
+    pub simulate_game: u64,
+    pub final_checks: u64,
+    /// Simulation cycles per `PROFILE_CHUNK_NODES` route nodes (`PROFILE_CHUNKS` only)
+    pub route_chunks: Vec<u64>,
+}
+
+impl CycleProfile {
+    /// Sum of all measured phases
+    pub fn total(&self) -> u64 {
+        self.read_input + self.hashing + self.parse_route + self.simulate_game + self.final_checks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(cred: u8, scores: &[i64]) -> LeaderboardEntry {
+        LeaderboardEntry { user_cred_hash: [cred; 32], scores: scores.to_vec(), route_nullifier: None }
+    }
+
+    #[test]
+    fn rank_keeps_best_per_player() {
+        let mut entries = vec![entry(1, &[10, 5]), entry(2, &[30]), entry(1, &[20]), entry(3, &[10, 7]), entry(2, &[40])];
+        Leaderboard::rank(&mut entries);
+        assert_eq!(entries, vec![entry(2, &[40]), entry(1, &[20]), entry(3, &[10, 7])]);
+
+        // Equal scores: lower credential hash first, whatever the input order
+        let mut tied = vec![entry(9, &[10]), entry(4, &[10])];
+        Leaderboard::rank(&mut tied);
+        assert_eq!(tied, vec![entry(4, &[10]), entry(9, &[10])]);
+    }
+}
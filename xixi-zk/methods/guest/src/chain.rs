@@ -0,0 +1,117 @@
+//! Chained proofs: a long route proven chunk by chunk, then joined
+//!
+//! A step proves "from the state committed as `state_in`, this chunk leads
+//! to `state_out`". The join verifies every step receipt with `env::verify`,
+//! checks the commitments link up from the initial state, and commits the
+//! same `Output` a whole-route proof would, with `chunk_image_id` set.
+
+use anyhow::{bail, ensure, Context, Result};
+use risc0_zkvm::guest::env;
+use risc0_zkvm::sha::Digest as ImageId;
+use rkyv::Archived;
+use sha2::{Digest, Sha256};
+use xixi_core::{ChainStart, ChainState, ChunkClaim, CHUNK_TAG, GENESIS_STATE, NULLIFIER_DOMAIN, OUTPUT_VERSION};
+
+use crate::model::{GameConfig, Output};
+use crate::{simulator, verifier};
+
+fn commitment(state: &ChainState, config_hash: &[u8; 32]) -> [u8; 32] {
+    Sha256::digest(state.commitment_bytes(config_hash)).into()
+}
+
+/// Route nullifier before the first node of the game (see `NULLIFIER_DOMAIN`)
+pub fn nullifier_seed(config_hash: &[u8; 32]) -> [u8; 32] {
+    Sha256::new().chain_update(NULLIFIER_DOMAIN).chain_update(config_hash).finalize().into()
+}
+
+/// Route nullifier `hash` carried on over the nodes of `route_bytes`
+pub fn extend_nullifier(mut hash: [u8; 32], route_bytes: &[u8]) -> [u8; 32] {
+    for node in route_bytes.chunks(4) {
+        hash = Sha256::new().chain_update(hash).chain_update(node).finalize().into();
+    }
+    hash
+}
+
+/// Prove one route chunk
+pub fn step(
+    config: &Archived<GameConfig>,
+    config_hash: [u8; 32],
+    user_cred_hash: [u8; 32],
+    route_bytes: &[u8],
+    start: Option<&ChainStart>,
+    challenge: Option<String>,
+) -> Result<ChunkClaim> {
+    let route = verifier::parse_route(route_bytes)?;
+    let Some(&last_node) = route.last() else {
+        bail!("Route chunk cannot be empty");
+    };
+
+    let state_out = simulator::simulate_chunk(config, start.map(|start| &start.state), &route)
+        .map_err(|e| anyhow::anyhow!("Game simulation failed: {:?}", e))?;
+    let route_in = start.map_or_else(|| nullifier_seed(&config_hash), |start| start.route);
+    Ok(ChunkClaim {
+        tag: CHUNK_TAG,
+        config_hash,
+        game_id: Sha256::digest(config.canonical_bytes()).into(),
+        state_in: start.map_or(GENESIS_STATE, |start| commitment(&start.state, &config_hash)),
+        state_out,
+        user_cred_hash,
+        challenge,
+        route_in,
+        route_out: extend_nullifier(route_in, route_bytes),
+        last_node,
+    })
+}
+
+/// Join chunk proofs, given by their journals in route order
+///
+/// The joined proof commits the challenge the chunks were proven for;
+/// `challenge` from the join input must be the same, if given.
+pub fn join(
+    image_id: [u8; 32],
+    journals: &[Vec<u8>],
+    user_cred_hash: [u8; 32],
+    challenge: Option<String>,
+) -> Result<Output> {
+    let mut previous: Option<ChunkClaim> = None;
+    for (i, journal) in journals.iter().enumerate() {
+        env::verify(ImageId::from(image_id), journal).expect("Chunk proof does not verify");
+        let claim: ChunkClaim = risc0_zkvm::serde::from_slice(journal).context("Invalid chunk journal")?;
+        ensure!(claim.tag == CHUNK_TAG, "Proof {} is not a chunk proof", i);
+        ensure!(claim.user_cred_hash == user_cred_hash, "Chunk {} was proven for another player", i);
+
+        let (expected, route) = match &previous {
+            None => (GENESIS_STATE, nullifier_seed(&claim.config_hash)),
+            Some(prev) => {
+                ensure!(prev.config_hash == claim.config_hash, "Chunk {} is for another game", i);
+                ensure!(prev.challenge == claim.challenge, "Chunk {} was proven for another challenge", i);
+                (commitment(&prev.state_out, &prev.config_hash), prev.route_out)
+            }
+        };
+        ensure!(claim.state_in == expected, "Chunk {} does not continue from the state before it", i);
+        ensure!(claim.route_in == route, "Chunk {} does not continue the route before it", i);
+        previous = Some(claim);
+    }
+
+    let last = previous.context("No chunks to join")?;
+    ensure!(challenge.is_none() || challenge == last.challenge, "Chunks were proven for another challenge");
+    ensure!(last.last_node == 1, "Route must end with node 1 (game termination node)");
+    verifier::verify_final_state(&last.state_out.player)?;
+    Ok(Output {
+        version: OUTPUT_VERSION,
+        config_hash: last.config_hash,
+        user_cred_hash,
+        scores: vec![verifier::calculate_score(&last.state_out.player)],
+        chunk_image_id: Some(image_id),
+        challenge: last.challenge,
+        // The route is not at hand when joining
+        route_commitment: None,
+        route_nullifier: Some(last.route_out),
+        route_ciphertext: None,
+        score_threshold: None,
+        disclosed: Vec::new(),
+        category: None,
+        route_steps: Vec::new(),
+        game_id: Some(last.game_id),
+    })
+}
@@ -1,3 +1,5 @@
+pub mod chain;
 pub mod model;
+pub mod profile;
 pub mod simulator;
 pub mod verifier;
\ No newline at end of file
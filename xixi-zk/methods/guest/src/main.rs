@@ -9,7 +9,11 @@ use risc0_zkvm::guest::env;
 use xixi_verifier::verifier;
 use anyhow::{bail, Result};
 use sha2::{Sha256, Digest};
+use xixi_core::{judge, ChainInput, Disclosure, OutputOptions, RouteCiphertext, ScoreThreshold};
+use xixi_core::{MAX_CATEGORY_ID_LEN, MAX_CHALLENGE_LEN, MAX_DISCLOSED, OUTPUT_VERSION};
+use xixi_verifier::chain;
 use xixi_verifier::model::Output;
+use xixi_verifier::profile::Profiler;
 
 // Configurable buffer size in MB - adjust based on actual config requirements
 const BUFFER_SIZE_MB: usize = 1; // Reduce from 16MB to 1MB by default
@@ -31,7 +35,16 @@ static mut ROUTE_BUFFER_BUF: AlignedBuffer = AlignedBuffer {
     data: [0u8; BUFFER_SIZE]
 };
 
-fn read_input() -> Result<(&'static [u8], [u8; 32], [u8; 32], &'static [u8])> {
+struct Input {
+    config_bytes: &'static [u8],
+    user_cred_hash: [u8; 32],
+    route_bytes: &'static [u8],
+    profile_mode: u32,
+    chain: ChainInput,
+    options: OutputOptions,
+}
+
+fn read_input() -> Result<Input> {
     // Read config bytes with length prefix into static buffer
     let config_len: u32 = env::read();
     if config_len > BUFFER_SIZE.try_into().unwrap() {
@@ -45,9 +58,6 @@ fn read_input() -> Result<(&'static [u8], [u8; 32], [u8; 32], &'static [u8])> {
         &CONFIG_BUFFER_BUF.data[..config_len]
     };
 
-    // Calculate config hash immediately after reading config bytes
-    let config_hash = Sha256::digest(config_bytes).into();
-
     // Read user credential hash (fixed 32 bytes)
     let mut user_cred_hash = [0u8; 32];
     env::read_slice(&mut user_cred_hash);
@@ -62,25 +72,120 @@ fn read_input() -> Result<(&'static [u8], [u8; 32], [u8; 32], &'static [u8])> {
         env::read_slice(&mut ROUTE_BUFFER_BUF.data[..route_bytes_len]);
         &ROUTE_BUFFER_BUF.data[..route_bytes_len]
     };
-    
-    Ok((config_bytes, config_hash, user_cred_hash, route_bytes))
+
+    // Read profiling mode (PROFILE_OFF for regular proving)
+    let profile_mode: u32 = env::read();
+
+    // Read chained proof mode (ChainInput::Off for a whole route)
+    let chain: ChainInput = env::read();
+
+    // Read what to add to the output (challenge, route commitment salt, judge)
+    // and the category rules to check
+    let options: OutputOptions = env::read();
+    if options.challenge.as_ref().is_some_and(|c| c.len() > MAX_CHALLENGE_LEN) {
+        bail!("Challenge exceeds size limit");
+    }
+    if options.disclose.len() > MAX_DISCLOSED {
+        bail!("Too many facts to disclose");
+    }
+    if options.category.as_ref().is_some_and(|c| c.id.len() > MAX_CATEGORY_ID_LEN) {
+        bail!("Category ID exceeds size limit");
+    }
+
+    Ok(Input { config_bytes, user_cred_hash, route_bytes, profile_mode, chain, options })
+}
+
+/// Replace the scores by whether they reach `threshold`, if given
+fn hide_scores(output: &mut Output, threshold: Option<i64>) {
+    if let Some(threshold) = threshold {
+        output.score_threshold = Some(ScoreThreshold::new(threshold, &output.scores));
+        output.scores.clear();
+    }
 }
 
 fn main() {
-    let (config_bytes, config_hash, user_cred_hash, route_bytes) = read_input()
+    let Input { config_bytes, user_cred_hash, route_bytes, profile_mode, chain, options } = read_input()
         .expect("Failed to read input");
+    let mut profiler = Profiler::new(profile_mode);
+
+    // Joining needs no config: the chunk journals carry its hash
+    if let ChainInput::Join { image_id, journals } = chain {
+        let mut output = chain::join(image_id, &journals, user_cred_hash, options.challenge).expect("Join failed");
+        hide_scores(&mut output, options.score_threshold);
+        env::commit(&output);
+        return;
+    }
+
+    // Calculate config hash over the raw rkyv bytes
+    let config_hash = Sha256::digest(config_bytes).into();
+    profiler.profile_mut().hashing = profiler.lap();
+
+    // Validate the archive before anything is read from it
+    let config = verifier::config_from_bytes(config_bytes).expect("Invalid config");
+
+    if let ChainInput::Step(start) = chain {
+        let claim = chain::step(config, config_hash, user_cred_hash, route_bytes, start.as_ref(), options.challenge)
+            .expect("Chunk verification failed");
+        env::commit(&claim);
+        return;
+    }
+
+    // Game ID over the canonical encoding, stable across rkyv versions
+    let game_id = Sha256::digest(config.canonical_bytes()).into();
+    profiler.profile_mut().hashing += profiler.lap();
 
     // Call verifier to parse route, simulate game and get scores
-    let scores = verifier::do_main(config_bytes, route_bytes)
+    let (scores, run) = verifier::do_main(config, route_bytes, options.category.as_ref(), &mut profiler)
         .expect("Verification failed");
 
+    // Make public what the prover asked for about the final state
+    let disclosed = options
+        .disclose
+        .iter()
+        .map(|&fact| Ok(Disclosure { fact, value: verifier::disclose(config, &run.state, fact)? }))
+        .collect::<Result<Vec<_>>>()
+        .expect("Disclosure failed");
+
+    // Steps of the route to show, the rest stays private
+    let route_steps = match &options.route_steps {
+        Some(selection) => selection.select(route_bytes).expect("Public steps do not fit the route"),
+        None => Vec::new(),
+    };
+
+    // Commit to the route without revealing it
+    let route_commitment = options.route_salt.map(|salt| Sha256::new().chain_update(salt).chain_update(route_bytes).finalize().into());
+
+    // Same for everyone proving this route, to spot copied routes
+    let route_nullifier = chain::extend_nullifier(chain::nullifier_seed(&config_hash), route_bytes);
+
+    // Encrypt the very route just simulated to the judge
+    let route_ciphertext = options.judge.map(|input| {
+        let ciphertext = judge::encrypt(&input.judge_key, &input.sender_secret, &config_hash, route_bytes)
+            .expect("Unusable judge key");
+        RouteCiphertext { judge_key: input.judge_key, ciphertext_hash: Sha256::digest(&ciphertext).into() }
+    });
+
     // Assemble final output structure here
-    let output = Output {
+    let mut output = Output {
+        version: OUTPUT_VERSION,
         config_hash,
         user_cred_hash,
         scores,
+        chunk_image_id: None,
+        challenge: options.challenge,
+        route_commitment,
+        route_nullifier: Some(route_nullifier),
+        route_ciphertext,
+        score_threshold: None,
+        disclosed,
+        category: options.category,
+        route_steps,
+        game_id: Some(game_id),
     };
+    hide_scores(&mut output, options.score_threshold);
 
     // Commit the full output structure
     env::commit(&output);
+
+    profiler.finish();
 }
@@ -8,5 +8,6 @@ pub use xixi_core::{
     Enemy,
     PlayerState,
     LevelUp,
-    Output
+    Output,
+    ChainState
 };
\ No newline at end of file
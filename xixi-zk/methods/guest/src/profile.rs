@@ -0,0 +1,58 @@
+//! Optional cycle profiling of the guest phases
+//!
+//! The profile goes to `PROFILE_FD` instead of the journal, so enabling it
+//! never changes what a receipt claims.
+
+use risc0_zkvm::guest::env::{self, FdWriter, Write};
+use xixi_core::{CycleProfile, PROFILE_CHUNKS, PROFILE_CHUNK_NODES, PROFILE_FD, PROFILE_OFF};
+
+pub struct Profiler {
+    mode: u32,
+    last: u64,
+    profile: CycleProfile,
+}
+
+impl Profiler {
+    /// Start profiling; everything before this call is accounted to `read_input`
+    pub fn new(mode: u32) -> Self {
+        let mut profiler = Self {
+            mode,
+            last: 0,
+            profile: CycleProfile::default(),
+        };
+        profiler.profile.read_input = profiler.lap();
+        profiler
+    }
+
+    #[inline]
+    pub fn enabled(&self) -> bool {
+        self.mode != PROFILE_OFF
+    }
+
+    /// Route chunk size when per-chunk counts are wanted
+    pub fn chunk_nodes(&self) -> Option<usize> {
+        (self.mode == PROFILE_CHUNKS).then_some(PROFILE_CHUNK_NODES)
+    }
+
+    /// Cycles elapsed since the previous lap (0 when profiling is off)
+    pub fn lap(&mut self) -> u64 {
+        if !self.enabled() {
+            return 0;
+        }
+        let now = env::cycle_count();
+        let elapsed = now - self.last;
+        self.last = now;
+        elapsed
+    }
+
+    pub fn profile_mut(&mut self) -> &mut CycleProfile {
+        &mut self.profile
+    }
+
+    /// Emit the collected profile to the host
+    pub fn finish(self) {
+        if self.enabled() {
+            FdWriter::new(PROFILE_FD, |_| {}).write(&self.profile);
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::model::{self, GameConfig, PlayerState};
+use crate::model::{self, ChainState, GameConfig, PlayerState};
 use anyhow::{bail, Result};
 use rkyv::Archived;
 
@@ -6,10 +6,50 @@ use rkyv::Archived;
 mod game_engine {
     use super::*;
 
+    /// Where a whole route ended
+    pub struct Run {
+        pub state: ChainState,
+        /// Highest level after any node, for category rules
+        pub peak_lv: u32,
+    }
+
     // Main game simulation entry point
-    pub fn simulate_game(config: &Archived<GameConfig>, route: &[u32]) -> Result<PlayerState> {
+    pub fn simulate_game(config: &Archived<GameConfig>, route: &[u32]) -> Result<Run> {
+        let mut game = Game::new(config);
+        game.execute_route(route)?;
+        Ok(game.finish())
+    }
+
+    /// Same as `simulate_game`, calling `on_chunk` after every `chunk_nodes` nodes
+    ///
+    /// Kept separate so the unprofiled hot loop stays untouched.
+    pub fn simulate_game_chunked(
+        config: &Archived<GameConfig>,
+        route: &[u32],
+        chunk_nodes: usize,
+        mut on_chunk: impl FnMut(),
+    ) -> Result<Run> {
+        let mut game = Game::new(config);
+        for chunk in route.chunks(chunk_nodes) {
+            game.execute_route(chunk)?;
+            on_chunk();
+        }
+        Ok(game.finish())
+    }
+
+    /// Run one route chunk from `start` (the initial state when `None`) and
+    /// return the state after it
+    pub fn simulate_chunk(
+        config: &Archived<GameConfig>,
+        start: Option<&ChainState>,
+        route: &[u32],
+    ) -> Result<ChainState> {
         let mut game = Game::new(config);
-        game.execute_route(route)
+        if let Some(start) = start {
+            game.state.restore(start)?;
+        }
+        game.execute_route(route)?;
+        Ok(game.state.snapshot())
     }
 
     // Top-level game orchestrator
@@ -60,8 +100,13 @@ mod game_engine {
 
             self.context.process_major_node(&mut self.state, node)?;
             self.state.mark_major_completed(node_idx);
+            self.state.peak_lv = self.state.peak_lv.max(self.state.player.lv);
             Ok(())
         }
+
+        fn finish(&self) -> Run {
+            Run { state: self.state.snapshot(), peak_lv: self.state.peak_lv }
+        }
     }
 
     // Player state with completion tracking
@@ -69,6 +114,7 @@ mod game_engine {
         player: PlayerState,
         completed_majors: Vec<bool>,
         completed_minors: Vec<bool>,
+        peak_lv: u32,
     }
 
     impl GameState {
@@ -81,6 +127,23 @@ mod game_engine {
                 player: PlayerState::from_init_stats(&config.init_stat),
                 completed_majors,
                 completed_minors: vec![false; config.minor_desc.len()],
+                peak_lv: config.init_stat.lv,
+            }
+        }
+
+        fn restore(&mut self, state: &ChainState) -> Result<()> {
+            self.player = state.player.clone();
+            self.peak_lv = state.player.lv;
+            self.completed_majors = unpack_bits(&state.majors, self.completed_majors.len())?;
+            self.completed_minors = unpack_bits(&state.minors, self.completed_minors.len())?;
+            Ok(())
+        }
+
+        fn snapshot(&self) -> ChainState {
+            ChainState {
+                player: self.player.clone(),
+                majors: pack_bits(&self.completed_majors),
+                minors: pack_bits(&self.completed_minors),
             }
         }
 
@@ -109,6 +172,21 @@ mod game_engine {
         }
     }
 
+    fn pack_bits(bits: &[bool]) -> Vec<u32> {
+        bits.chunks(32)
+            .map(|chunk| chunk.iter().enumerate().fold(0, |word, (i, &bit)| word | (bit as u32) << i))
+            .collect()
+    }
+
+    fn unpack_bits(words: &[u32], len: usize) -> Result<Vec<bool>> {
+        // Exactly the words `pack_bits` makes for `len` nodes, no stray bits
+        let stray = !len.is_multiple_of(32) && words.last().is_some_and(|w| w >> (len % 32) != 0);
+        if words.len() != len.div_ceil(32) || stray {
+            bail!("Chain state does not fit the config");
+        }
+        Ok((0..len).map(|i| words[i / 32] >> (i % 32) & 1 == 1).collect())
+    }
+
     // Game context with immutable references
     struct GameContext<'a> {
         config: &'a Archived<GameConfig>,
@@ -470,4 +548,4 @@ mod level_up {
 }
 
 // Re-export the main function
-pub use game_engine::simulate_game;
+pub use game_engine::{simulate_chunk, simulate_game, simulate_game_chunked, Run};
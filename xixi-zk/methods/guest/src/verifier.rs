@@ -1,40 +1,125 @@
 use anyhow::{bail, Result};
 use crate::{model, simulator};
-use model::{GameConfig};
-use rkyv::{Archived, archived_root};
+use crate::profile::Profiler;
+use model::{ChainState, GameConfig};
+use xixi_core::{AttrType, Category, Fact};
+use rkyv::{Archived, check_archived_root};
 
 /// Zero-copy conversion from byte slice to Archived<GameConfig>
 ///
-/// This performs a direct zero-copy deserialization without validation
-/// for maximum performance. The caller must ensure the input is valid
-/// and properly aligned.
-pub fn config_from_bytes(bytes: &[u8]) -> &Archived<GameConfig> {
+/// The archive is validated first: the game ID committed from it must be
+/// the one of a well-formed config, not of whatever the bytes point to.
+pub fn config_from_bytes(bytes: &[u8]) -> Result<&Archived<GameConfig>> {
     // Basic alignment check for safety (should be at least 16-byte aligned for rkyv)
     assert!(
         bytes.as_ptr() as usize % 16 == 0,
         "Config buffer must be 16-byte aligned for rkyv deserialization"
     );
-    
-    unsafe { archived_root::<GameConfig>(bytes) }
+
+    check_archived_root::<GameConfig>(bytes).map_err(|e| anyhow::anyhow!("Invalid config archive: {}", e))
 }
 
-pub fn do_main(config_bytes: &[u8], route_bytes: &[u8]) -> Result<Vec<i64>> {
+/// Simulate the route and check it completes the game, and follows the
+/// rules of `category` if given; returns the scores and where the route ended
+pub fn do_main(
+    config: &Archived<GameConfig>,
+    route_bytes: &[u8],
+    category: Option<&Category>,
+    profiler: &mut Profiler,
+) -> Result<(Vec<i64>, simulator::Run)> {
     // Parse and verify route
     let route = parse_route(route_bytes)?;
     verify_route(&route)?;
-    
-    // Safe archived root access with validation
-    let config = config_from_bytes(config_bytes);
+    profiler.profile_mut().parse_route = profiler.lap();
 
     // Simulate game using zero-copy config
-    let final_state = simulator::simulate_game(&config, &route)
-        .map_err(|e| anyhow::anyhow!("Game simulation failed: {:?}", e))?;
+    let final_state = match profiler.chunk_nodes() {
+        None => simulator::simulate_game(config, &route),
+        Some(chunk_nodes) => {
+            let mut route_chunks = Vec::new();
+            let state = simulator::simulate_game_chunked(config, &route, chunk_nodes, || {
+                route_chunks.push(profiler.lap())
+            });
+            profiler.profile_mut().route_chunks = route_chunks;
+            state
+        }
+    }
+    .map_err(|e| anyhow::anyhow!("Game simulation failed: {:?}", e))?;
+    let chunk_total: u64 = profiler.profile_mut().route_chunks.iter().sum();
+    profiler.profile_mut().simulate_game = chunk_total + profiler.lap();
 
     // Verify final state
-    verify_final_state(&final_state)?;
+    verify_final_state(&final_state.state.player)?;
+    if let Some(category) = category {
+        check_category(config, &route, &final_state, category)?;
+    }
 
     // Calculate score and return
-    Ok(vec![calculate_score(&final_state)])
+    let scores = vec![calculate_score(&final_state.state.player)];
+    profiler.profile_mut().final_checks = profiler.lap();
+    Ok((scores, final_state))
+}
+
+/// Value of `fact` in the final state of a game; flags are 0 or 1
+pub fn disclose(config: &Archived<GameConfig>, state: &ChainState, fact: Fact) -> Result<i64> {
+    let p = &state.player;
+    let nodes = config.major_desc.len();
+    let completed = || (0..nodes).filter(|&node| state.major_completed(node));
+    Ok(match fact {
+        Fact::Stat(attr) => match attr {
+            AttrType::Hp => p.hp as i64,
+            AttrType::Atk => p.atk as i64,
+            AttrType::Def => p.def as i64,
+            AttrType::Mdef => p.mdef as i64,
+            AttrType::Exp => p.exp as i64,
+            AttrType::Lv => p.lv as i64,
+            AttrType::Salt => p.salt as i64,
+            AttrType::BigSalt => p.big_salt as i64,
+        },
+        Fact::EnemiesDefeated => enemy_nodes(config).filter(|&node| state.major_completed(node)).count() as i64,
+        // Node 0 is the start and always completed
+        Fact::NodesVisited => completed().filter(|&node| node != 0).count() as i64,
+        Fact::AllNodesVisited => (completed().count() == nodes) as i64,
+        Fact::NodeVisited(node) => {
+            if node as usize >= nodes {
+                bail!("Node {} is not in the game", node);
+            }
+            state.major_completed(node as usize) as i64
+        }
+    })
+}
+
+/// Enemy major nodes, leaving out the start which is never fought
+fn enemy_nodes(config: &Archived<GameConfig>) -> impl Iterator<Item = usize> + '_ {
+    (1..config.major_desc.len())
+        .filter(|&node| matches!(config.major_desc[node], Archived::<model::MajorDesc>::Enemy(_)))
+}
+
+/// Check the rules of a contest category over a whole route
+pub fn check_category(config: &Archived<GameConfig>, route: &[u32], run: &simulator::Run, category: &Category) -> Result<()> {
+    let state = &run.state;
+    let nodes = config.major_desc.len();
+    for &node in &category.forbidden_nodes {
+        if node as usize >= nodes {
+            bail!("Forbidden node {} is not in the game", node);
+        }
+        if route.contains(&node) {
+            bail!("Route enters forbidden node {}", node);
+        }
+    }
+    if category.all_nodes && !(0..nodes).all(|node| state.major_completed(node)) {
+        bail!("Category {} needs every node completed", category.id);
+    }
+    if category.all_enemies && !enemy_nodes(config).all(|node| state.major_completed(node)) {
+        bail!("Category {} needs every enemy defeated", category.id);
+    }
+    if category.pacifist && enemy_nodes(config).any(|node| state.major_completed(node)) {
+        bail!("Category {} allows no enemy fought", category.id);
+    }
+    if category.no_level_up && run.peak_lv > config.init_stat.lv {
+        bail!("Category {} allows no level up", category.id);
+    }
+    Ok(())
 }
 
 /// Validate game route meets requirements
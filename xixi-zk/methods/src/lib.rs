@@ -1 +1,4 @@
 include!(concat!(env!("OUT_DIR"), "/methods.rs"));
+
+/// How the guests were built: `docker` (reproducible), `local` or `skipped`
+pub const GUEST_BUILD_MODE: &str = env!("GUEST_BUILD_MODE");
@@ -0,0 +1,352 @@
+//! Proof file format
+//!
+//! Version 1 layout, integers little-endian:
+//!
+//! | Field            | Size        | Content                                      |
+//! |------------------|-------------|----------------------------------------------|
+//! | magic            | 8           | `XIXIPROF`                                   |
+//! | version          | u16         | `1`                                          |
+//! | compression      | u8          | `0` none, `1` deflate (applies to receipt)   |
+//! | flags            | u8          | `1`: credential withheld (anonymous proof)   |
+//! | image ID         | 32          | guest image the receipt was proven for       |
+//! | game             | 32          | config hash from the journal                 |
+//! | risc0 version    | u8 + bytes  | risc0-zkvm version of the prover, UTF-8      |
+//! | user credential  | u32 + bytes | original credential, hashed in the journal   |
+//! | signature        | u16 + bytes | by the credential's key, empty if unsigned   |
+//! | route ciphertext | u32 + bytes | route encrypted to a judge, usually empty    |
+//! | receipt          | u64 + bytes | bincode `Receipt`, compressed if flagged     |
+//!
+//! The header is plain bytes, so it stays readable when bincode or risc0
+//! change and a receipt no longer decodes. Files without the magic are read
+//! as version 0: the bare bincode `WrappedReceipt` written before. A
+//! withheld credential is written empty and can be put back from its reveal
+//! file (see `reveal`). The salt of an anonymous proof is never written: a
+//! salt taken from the file could shift bytes out of the credential. A receipt inflates to at most `MAX_RECEIPT_LEN`.
+
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use risc0_zkvm::{sha::Digest, Receipt};
+use xixi_core::{ChunkClaim, Leaderboard, LegacyOutput, Output, CHUNK_TAG};
+
+pub const MAGIC: &[u8; 8] = b"XIXIPROF";
+pub const FORMAT_VERSION: u16 = 1;
+
+/// Largest receipt a proof file may inflate to
+pub const MAX_RECEIPT_LEN: u64 = 256 << 20;
+
+const COMPRESSION_NONE: u8 = 0;
+const COMPRESSION_DEFLATE: u8 = 1;
+
+const FLAG_CRED_WITHHELD: u8 = 1;
+
+/// Version 0 proof file
+#[derive(serde::Serialize, serde::Deserialize)]
+struct WrappedReceipt {
+    receipt: Receipt,
+    user_cred: Vec<u8>,
+}
+
+/// Proof file contents
+pub struct ProofEnvelope {
+    /// Format version the file was read with (0 for legacy files)
+    pub version: u16,
+    /// Image ID from the header, unknown for version 0
+    pub image_id: Option<Digest>,
+    /// risc0-zkvm version of the prover, unknown for version 0
+    pub risc0_version: Option<String>,
+    pub compressed: bool,
+    pub receipt: Receipt,
+    /// `None` for an anonymous proof until its reveal is applied
+    pub user_cred: Option<Vec<u8>>,
+    /// Salt of the credential commitment, only set by `Reveal::apply`
+    pub cred_salt: Vec<u8>,
+    /// Signature over the journal digest when the credential is a key
+    pub signature: Vec<u8>,
+    /// Route encrypted to the judge named in the journal
+    pub route_ciphertext: Vec<u8>,
+}
+
+impl ProofEnvelope {
+    /// New proof file, proven with this build of risc0-zkvm
+    pub fn new(image_id: impl Into<Digest>, receipt: Receipt, user_cred: Option<Vec<u8>>) -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            image_id: Some(image_id.into()),
+            risc0_version: Some(risc0_zkvm::VERSION.to_string()),
+            compressed: false,
+            receipt,
+            user_cred,
+            cred_salt: Vec::new(),
+            signature: Vec::new(),
+            route_ciphertext: Vec::new(),
+        }
+    }
+
+    /// Journal of a player proof, also in the layout of guest 0.1.0
+    pub fn output(&self) -> Result<Output> {
+        let journal = &self.receipt.journal;
+        if Output::is_current(&journal.bytes) {
+            return Ok(journal.decode()?);
+        }
+        match self.first_word() {
+            Some(word) if word > 0xff => bail!("Unknown journal version {:#010x}", word),
+            _ => Ok(journal.decode::<LegacyOutput>().map(Output::from)?),
+        }
+    }
+
+    fn first_word(&self) -> Option<u32> {
+        let word = self.receipt.journal.bytes.get(..4)?;
+        Some(u32::from_le_bytes(word.try_into().ok()?))
+    }
+
+    /// Journal of a route chunk proof (`host prove-chunk`)
+    pub fn chunk_claim(&self) -> Result<ChunkClaim> {
+        let claim: ChunkClaim = self.receipt.journal.decode()?;
+        if claim.tag != CHUNK_TAG {
+            bail!("Not a chunk proof");
+        }
+        Ok(claim)
+    }
+
+    pub fn is_chunk(&self) -> bool {
+        self.first_word() == Some(CHUNK_TAG)
+    }
+
+    /// Journal of an aggregated proof (`host aggregate`)
+    pub fn leaderboard(&self) -> Result<Leaderboard> {
+        Ok(self.receipt.journal.decode()?)
+    }
+
+    /// Config hash at the start of the journal, for player proofs (after
+    /// their version), leaderboards and chunks (after their tag) alike
+    pub fn game(&self) -> Result<[u8; 32]> {
+        // risc0 serde spends one word per byte
+        let start = if self.is_chunk() || Output::is_current(&self.receipt.journal.bytes) { 4 } else { 0 };
+        let words = self.receipt.journal.bytes.get(start..start + 32 * 4).context("Journal too short")?;
+        Ok(risc0_zkvm::serde::from_slice(words)?)
+    }
+
+    /// Encode as the current format version
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let image_id = self.image_id.context("Image ID is required to write a proof file")?;
+        if !self.cred_salt.is_empty() {
+            bail!("A revealed anonymous proof is not written with its credential; ship the reveal file instead");
+        }
+        let config_hash = self.game()?;
+        let risc0_version = self.risc0_version.as_deref().unwrap_or(risc0_zkvm::VERSION);
+
+        let mut receipt = bincode::serialize(&self.receipt)?;
+        if self.compressed {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+            encoder.write_all(&receipt)?;
+            receipt = encoder.finish()?;
+        }
+
+        let user_cred = self.user_cred.as_deref().unwrap_or_default();
+        let mut bytes = Vec::with_capacity(receipt.len() + user_cred.len() + 128);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bytes.push(if self.compressed { COMPRESSION_DEFLATE } else { COMPRESSION_NONE });
+        bytes.push(if self.user_cred.is_none() { FLAG_CRED_WITHHELD } else { 0 });
+        bytes.extend_from_slice(image_id.as_bytes());
+        bytes.extend_from_slice(&config_hash);
+        bytes.push(u8::try_from(risc0_version.len()).context("risc0 version too long")?);
+        bytes.extend_from_slice(risc0_version.as_bytes());
+        bytes.extend_from_slice(&u32::try_from(user_cred.len())?.to_le_bytes());
+        bytes.extend_from_slice(user_cred);
+        bytes.extend_from_slice(&u16::try_from(self.signature.len()).context("Signature too long")?.to_le_bytes());
+        bytes.extend_from_slice(&self.signature);
+        bytes.extend_from_slice(&u32::try_from(self.route_ciphertext.len())?.to_le_bytes());
+        bytes.extend_from_slice(&self.route_ciphertext);
+        bytes.extend_from_slice(&(receipt.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&receipt);
+        Ok(bytes)
+    }
+
+    /// Decode any known format version
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if !bytes.starts_with(MAGIC) {
+            let wrapped: WrappedReceipt = bincode::deserialize(bytes)
+                .context("Not a proof file (no XIXIPROF header, and not a version 0 proof)")?;
+            return Ok(Self {
+                version: 0,
+                image_id: None,
+                risc0_version: None,
+                compressed: false,
+                receipt: wrapped.receipt,
+                user_cred: Some(wrapped.user_cred),
+                cred_salt: Vec::new(),
+                signature: Vec::new(),
+                route_ciphertext: Vec::new(),
+            });
+        }
+
+        let mut r = Reader { bytes, pos: MAGIC.len() };
+        let version = u16::from_le_bytes(r.array()?);
+        if version != FORMAT_VERSION {
+            bail!("Unsupported proof format version {} (this build reads 0 and {})", version, FORMAT_VERSION);
+        }
+        let compression = r.take(1)?[0];
+        let flags = r.take(1)?[0];
+        if flags & !FLAG_CRED_WITHHELD != 0 {
+            bail!("Unknown proof flags {:#04x}", flags);
+        }
+        let image_id = Digest::from(r.array::<32>()?);
+        let config_hash: [u8; 32] = r.array()?;
+        let len = r.take(1)?[0] as usize;
+        let risc0_version = String::from_utf8(r.take(len)?.to_vec()).context("Invalid risc0 version")?;
+        let len = u32::from_le_bytes(r.array()?) as usize;
+        let user_cred = r.take(len)?.to_vec();
+        let user_cred = (flags & FLAG_CRED_WITHHELD == 0).then_some(user_cred);
+        let len = u16::from_le_bytes(r.array()?) as usize;
+        let signature = r.take(len)?.to_vec();
+        let len = u32::from_le_bytes(r.array()?) as usize;
+        let route_ciphertext = r.take(len)?.to_vec();
+        let len = usize::try_from(u64::from_le_bytes(r.array()?))?;
+        let mut receipt = r.take(len)?.to_vec();
+        if r.pos != bytes.len() {
+            bail!("Trailing bytes after proof");
+        }
+
+        let compressed = match compression {
+            COMPRESSION_NONE => false,
+            COMPRESSION_DEFLATE => {
+                receipt = inflate(&receipt, MAX_RECEIPT_LEN)?;
+                true
+            }
+            other => bail!("Unknown proof compression {}", other),
+        };
+        let receipt: Receipt = bincode::deserialize(&receipt).with_context(|| {
+            format!(
+                "Failed to decode receipt for game {}, written by risc0-zkvm {} (this build uses {})",
+                hex::encode(config_hash),
+                risc0_version,
+                risc0_zkvm::VERSION
+            )
+        })?;
+
+        Ok(Self {
+            version,
+            image_id: Some(image_id),
+            risc0_version: Some(risc0_version),
+            compressed,
+            receipt,
+            user_cred,
+            cred_salt: Vec::new(),
+            signature,
+            route_ciphertext,
+        })
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).with_context(|| format!("Failed to read proof {}", path.display()))?;
+        Self::from_bytes(&bytes).with_context(|| format!("Invalid proof file {}", path.display()))
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        fs::write(path, self.to_bytes()?)?;
+        Ok(())
+    }
+}
+
+/// Deflated bytes, refusing to inflate beyond `limit`
+fn inflate(bytes: &[u8], limit: u64) -> Result<Vec<u8>> {
+    let mut inflated = Vec::new();
+    DeflateDecoder::new(bytes)
+        .take(limit + 1)
+        .read_to_end(&mut inflated)
+        .context("Corrupt compressed receipt")?;
+    if inflated.len() as u64 > limit {
+        bail!("Compressed receipt inflates beyond {} bytes", limit);
+    }
+    Ok(inflated)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(len).filter(|&end| end <= self.bytes.len());
+        let Some(end) = end else {
+            bail!("Proof file is truncated");
+        };
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.take(N)?.try_into()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use risc0_zkvm::{FakeReceipt, InnerReceipt, ReceiptClaim};
+
+    use super::*;
+
+    /// Unproven receipt whose journal starts with the config hash, like a
+    /// guest 0.1.0 journal
+    fn fake_receipt() -> Receipt {
+        let words = risc0_zkvm::serde::to_vec(&[7u8; 32]).unwrap();
+        let journal: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+        let claim = ReceiptClaim::ok(Digest::ZERO, journal.clone());
+        Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), journal)
+    }
+
+    #[test]
+    fn reads_version_0() {
+        let wrapped = WrappedReceipt { receipt: fake_receipt(), user_cred: b"user1".to_vec() };
+        let proof = ProofEnvelope::from_bytes(&bincode::serialize(&wrapped).unwrap()).unwrap();
+        assert_eq!(proof.version, 0);
+        assert_eq!((proof.image_id, proof.risc0_version.as_deref()), (None, None));
+        assert_eq!(proof.user_cred.as_deref(), Some(&b"user1"[..]));
+        assert_eq!(proof.receipt.journal.bytes, wrapped.receipt.journal.bytes);
+        assert_eq!(proof.game().unwrap(), [7; 32]);
+
+        assert!(ProofEnvelope::from_bytes(b"not a proof").is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut proof = ProofEnvelope::new(Digest::ZERO, fake_receipt(), None);
+        proof.compressed = true;
+        proof.signature = vec![2; 64];
+        proof.route_ciphertext = vec![3; 100];
+        let bytes = proof.to_bytes().unwrap();
+        assert_eq!(&bytes[..8], MAGIC);
+
+        let read = ProofEnvelope::from_bytes(&bytes).unwrap();
+        assert_eq!((read.version, read.image_id, read.compressed), (FORMAT_VERSION, Some(Digest::ZERO), true));
+        assert_eq!(read.user_cred, None);
+        assert_eq!(read.signature, proof.signature);
+        assert_eq!(read.route_ciphertext, proof.route_ciphertext);
+        assert_eq!(read.receipt.journal.bytes, proof.receipt.journal.bytes);
+
+        assert!(ProofEnvelope::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        proof.cred_salt = vec![1; 16];
+        assert!(proof.to_bytes().is_err());
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(ProofEnvelope::from_bytes(&trailing).is_err());
+    }
+
+    #[test]
+    fn inflate_is_capped() {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&[0; 4096]).unwrap();
+        let deflated = encoder.finish().unwrap();
+
+        assert_eq!(inflate(&deflated, 4096).unwrap().len(), 4096);
+        assert!(inflate(&deflated, 4095).is_err());
+    }
+}
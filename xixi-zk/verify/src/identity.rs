@@ -0,0 +1,143 @@
+//! Public-key identities
+//!
+//! A credential of the form `ed25519:<hex public key>` or
+//! `secp256k1:<hex compressed SEC1 public key>` is an identity. The journal
+//! commits its hash like any credential, and the proof file carries a
+//! signature by the key over the journal digest (SHA-256 of the journal
+//! bytes). A valid signature shows the submitter holds the key, so a leaked
+//! route cannot be proven under somebody else's identity.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use ed25519_dalek::Verifier as _;
+use k256::ecdsa::signature::hazmat::{PrehashSigner, PrehashVerifier};
+use risc0_zkvm::Receipt;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+/// Digest the identity signs
+pub fn journal_digest(receipt: &Receipt) -> [u8; 32] {
+    Sha256::digest(&receipt.journal.bytes).into()
+}
+
+fn split_kind(text: &str) -> Option<(&str, &str)> {
+    let (kind, hex) = text.trim_end_matches('\n').split_once(':')?;
+    matches!(kind, "ed25519" | "secp256k1").then_some((kind, hex))
+}
+
+/// Public key an identity credential names
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Identity {
+    Ed25519(ed25519_dalek::VerifyingKey),
+    Secp256k1(k256::ecdsa::VerifyingKey),
+}
+
+impl Identity {
+    /// Parse a credential; `Ok(None)` for a plain credential that names no key
+    pub fn from_credential(cred: &[u8]) -> Result<Option<Self>> {
+        let Some((kind, key)) = std::str::from_utf8(cred).ok().and_then(split_kind) else {
+            return Ok(None);
+        };
+        let key = hex::decode(key).with_context(|| format!("Invalid {} public key hex", kind))?;
+        Ok(Some(match kind {
+            "ed25519" => {
+                let key = key.as_slice().try_into().context("ed25519 public key must be 32 bytes")?;
+                Self::Ed25519(ed25519_dalek::VerifyingKey::from_bytes(key).context("Invalid ed25519 public key")?)
+            }
+            _ => Self::Secp256k1(
+                k256::ecdsa::VerifyingKey::from_sec1_bytes(&key).context("Invalid secp256k1 public key")?,
+            ),
+        }))
+    }
+
+    /// Credential file contents for this identity
+    pub fn to_credential(&self) -> String {
+        match self {
+            Self::Ed25519(key) => format!("ed25519:{}\n", hex::encode(key.as_bytes())),
+            Self::Secp256k1(key) => format!("secp256k1:{}\n", hex::encode(key.to_sec1_bytes())),
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Ed25519(_) => "ed25519",
+            Self::Secp256k1(_) => "secp256k1",
+        }
+    }
+
+    /// Check `signature` over `digest`: 64 bytes, ed25519 or ECDSA `r || s`
+    /// over the digest as prehash
+    pub fn verify(&self, digest: &[u8; 32], signature: &[u8]) -> Result<()> {
+        if signature.is_empty() {
+            bail!("Proof is not signed by its {} identity", self.kind());
+        }
+        match self {
+            Self::Ed25519(key) => {
+                let signature = ed25519_dalek::Signature::from_slice(signature).context("Malformed signature")?;
+                key.verify(digest, &signature).context("Bad signature")
+            }
+            Self::Secp256k1(key) => {
+                let signature = k256::ecdsa::Signature::from_slice(signature).context("Malformed signature")?;
+                key.verify_prehash(digest, &signature).context("Bad signature")
+            }
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({"type": self.kind(), "credential": self.to_credential().trim_end()})
+    }
+}
+
+/// Secret key of an identity, stored as `<kind>:<hex secret>`
+pub enum SigningKey {
+    Ed25519(ed25519_dalek::SigningKey),
+    Secp256k1(k256::ecdsa::SigningKey),
+}
+
+impl SigningKey {
+    /// Key of `kind` (`ed25519` or `secp256k1`) from 32 secret bytes
+    pub fn from_secret(kind: &str, secret: &[u8; 32]) -> Result<Self> {
+        Ok(match kind {
+            "ed25519" => Self::Ed25519(ed25519_dalek::SigningKey::from_bytes(secret)),
+            "secp256k1" => Self::Secp256k1(
+                k256::ecdsa::SigningKey::from_slice(secret).context("Secret is not a valid secp256k1 scalar")?,
+            ),
+            _ => bail!("Unknown key type '{}', expected ed25519 or secp256k1", kind),
+        })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).with_context(|| format!("Failed to read key {}", path.display()))?;
+        let (kind, secret) = split_kind(&text).context("Key file must hold <ed25519|secp256k1>:<hex secret>")?;
+        let secret = hex::decode(secret).ok().and_then(|s| s.try_into().ok()).context("Secret must be 32 hex bytes")?;
+        Self::from_secret(kind, &secret)
+    }
+
+    /// Key file contents
+    pub fn to_file_string(&self) -> String {
+        match self {
+            Self::Ed25519(key) => format!("ed25519:{}\n", hex::encode(key.to_bytes())),
+            Self::Secp256k1(key) => format!("secp256k1:{}\n", hex::encode(key.to_bytes())),
+        }
+    }
+
+    pub fn identity(&self) -> Identity {
+        match self {
+            Self::Ed25519(key) => Identity::Ed25519(key.verifying_key()),
+            Self::Secp256k1(key) => Identity::Secp256k1(*key.verifying_key()),
+        }
+    }
+
+    pub fn sign(&self, digest: &[u8; 32]) -> Result<Vec<u8>> {
+        Ok(match self {
+            Self::Ed25519(key) => ed25519_dalek::Signer::sign(key, digest).to_bytes().to_vec(),
+            Self::Secp256k1(key) => {
+                let signature: k256::ecdsa::Signature = key.sign_prehash(digest)?;
+                signature.to_bytes().to_vec()
+            }
+        })
+    }
+}
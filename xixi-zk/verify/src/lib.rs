@@ -0,0 +1,59 @@
+//! Proof verification for xixi-zk without the prover
+//!
+//! Reads proof files, checks receipts against registered guest image IDs
+//! and applies a verification policy. Used by `host` and by the standalone
+//! `xixi-verify` binary.
+
+pub mod envelope;
+pub mod identity;
+pub mod policy;
+pub mod registry;
+pub mod reveal;
+
+use anyhow::{Context, Result};
+use risc0_zkvm::sha::Digest;
+
+use envelope::ProofEnvelope;
+use policy::{Policy, Reason, Verdict};
+use registry::Registry;
+use reveal::Reveal;
+
+/// Verify `path` with the policy given by `args` (and `--reveal <file>`),
+/// print the JSON verdict and return the process exit code
+///
+/// `current_image_id` is accepted besides the registry (the guest compiled
+/// into `host`, or `registry::current_image_id` for `xixi-verify`).
+pub fn verify_cli(path: &str, args: &[String], current_image_id: Option<Digest>) -> Result<i32> {
+    // `--reveal <file>` puts back the credential of an anonymous proof
+    let mut args = args.to_vec();
+    let reveal = match args.iter().position(|a| a == "--reveal") {
+        Some(i) => {
+            let path = args.get(i + 1).context("--reveal requires a value")?.clone();
+            args.drain(i..i + 2);
+            Some(Reveal::load(path)?)
+        }
+        None => None,
+    };
+    let policy = Policy::from_args(&args)?;
+
+    let mut wrapped = ProofEnvelope::read(path)?;
+    if wrapped.version == 0 {
+        eprintln!("Note: version 0 proof file without header, rewrite it with a current prove");
+    }
+
+    // Output verdict as JSON, exit code tells the failure class
+    let verdict = match reveal.map(|reveal| reveal.apply(&mut wrapped)).transpose() {
+        Ok(_) => policy.verify(&wrapped, current_image_id, &Registry::load()?),
+        Err(e) => Verdict {
+            reason: Some(Reason::CredentialRejected),
+            message: format!("{:#}", e),
+            details: serde_json::json!({}),
+            claim: None,
+        },
+    };
+    println!("{}", verdict.to_json());
+    if verdict.reason.is_some() {
+        eprintln!("Error: {}", verdict.message);
+    }
+    Ok(verdict.exit_code())
+}
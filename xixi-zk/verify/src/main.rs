@@ -0,0 +1,12 @@
+use anyhow::Result;
+
+fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 2 || args[1].starts_with("--") {
+        eprintln!("Usage: {} <input.bin> [--policy <policy.toml>] [--game <hash>[=name]]... [--image-id <hex>]... [--min-score <n>] [--cred-pattern <regex>] [--cred-min-len <n>] [--cred-max-len <n>] [--require-signature] [--challenge <text>] [--category <id|file>] [--reveal <reveal.json>]", args[0]);
+        std::process::exit(1);
+    }
+
+    let code = xixi_verify::verify_cli(&args[1], &args[2..], xixi_verify::registry::current_image_id()?)?;
+    std::process::exit(code);
+}
@@ -0,0 +1,724 @@
+//! Verification policy for `host verify` and `xixi-verify`
+//!
+//! A policy says which games, guest images, scores and credentials a
+//! verifier accepts. It is read from a TOML file (`--policy`) and/or flags:
+//!
+//! ```toml
+//! image_ids = ["5be9bd21c4e75c2b4ccef0e7b6308f5ad5352940b3ccd83aaf22a2a229f67b61"]
+//! min_score = 40000
+//! challenge = "spring-cup-2025"
+//!
+//! [games]
+//! 5cc8681fb14dd7a453b3cc0d673310cdc22f3039c5e87f1b70998448d75aaa30 = "xixi"
+//!
+//! [credential]
+//! pattern = "user[0-9]+"
+//! max_len = 32
+//! require_signature = false
+//! ```
+//!
+//! Anonymous proofs (see `reveal`) only pass a policy without credential
+//! rules. Every rule is optional: no games means any game, no image IDs means every
+//! image in the registry (plus the compiled-in guest of `host`).
+
+use std::collections::BTreeMap;
+use std::fs;
+
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use risc0_zkvm::sha::Digest;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest as _, Sha256};
+use xixi_core::{Category, Disclosure, RouteStep, ScoreThreshold};
+
+use crate::envelope::ProofEnvelope;
+use crate::identity::{journal_digest, Identity};
+use crate::registry::Registry;
+use crate::reveal::{credential_commitment, SALTED_CRED_DOMAIN};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CredentialRules {
+    /// Regex the whole credential must match (trailing newline ignored)
+    pub pattern: Option<String>,
+    pub min_len: Option<usize>,
+    pub max_len: Option<usize>,
+    /// Only accept public-key identities with a valid signature
+    #[serde(default)]
+    pub require_signature: bool,
+}
+
+impl CredentialRules {
+    /// Whether any rule needs to see the credential
+    fn restricts(&self) -> bool {
+        self.pattern.is_some() || self.min_len.is_some() || self.max_len.is_some() || self.require_signature
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    /// Accepted config hashes or game IDs (hex) with their game names
+    #[serde(default)]
+    pub games: BTreeMap<String, String>,
+    /// Accepted guest image IDs (hex)
+    #[serde(default)]
+    pub image_ids: Vec<String>,
+    /// Lowest accepted `scores[0]`
+    pub min_score: Option<i64>,
+    #[serde(default)]
+    pub credential: CredentialRules,
+    /// Challenge the proof must be bound to (`prove --challenge`)
+    pub challenge: Option<String>,
+    /// Category the proof must be for (`prove --category`), with all its
+    /// rules: a predefined ID, a category JSON file or an inline table
+    #[serde(default, deserialize_with = "category_spec")]
+    pub category: Option<Category>,
+}
+
+/// Predefined category by ID, otherwise a category JSON file
+pub fn load_category(spec: &str) -> Result<Category> {
+    if let Some(category) = Category::builtin(spec) {
+        return Ok(category);
+    }
+    let text = fs::read_to_string(spec)
+        .with_context(|| format!("'{}' is neither a category ({}) nor a category file", spec, Category::BUILTIN.join(", ")))?;
+    serde_json::from_str(&text).with_context(|| format!("Invalid category {}", spec))
+}
+
+/// `category = "<id|file.json>"` or a `[category]` table with the rules
+fn category_spec<'de, D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Option<Category>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Spec {
+        Named(String),
+        Rules(Category),
+    }
+    match Spec::deserialize(deserializer)? {
+        Spec::Named(spec) => load_category(&spec).map(Some).map_err(|e| serde::de::Error::custom(format!("{:#}", e))),
+        Spec::Rules(category) => Ok(Some(category)),
+    }
+}
+
+/// Why a proof was rejected; each class has its own exit code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    InvalidProof,
+    ImageNotAllowed,
+    GameNotAllowed,
+    ScoreTooLow,
+    CredentialRejected,
+    ChallengeMismatch,
+    SignatureInvalid,
+    CategoryMismatch,
+}
+
+impl Reason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Reason::InvalidProof => "invalid_proof",
+            Reason::ImageNotAllowed => "image_not_allowed",
+            Reason::GameNotAllowed => "game_not_allowed",
+            Reason::ScoreTooLow => "score_too_low",
+            Reason::CredentialRejected => "credential_rejected",
+            Reason::ChallengeMismatch => "challenge_mismatch",
+            Reason::SignatureInvalid => "signature_invalid",
+            Reason::CategoryMismatch => "category_mismatch",
+        }
+    }
+
+    /// Process exit code; 1 stays reserved for usage and I/O errors
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Reason::InvalidProof => 2,
+            Reason::ImageNotAllowed => 3,
+            Reason::GameNotAllowed => 4,
+            Reason::ScoreTooLow => 5,
+            Reason::CredentialRejected => 6,
+            Reason::ChallengeMismatch => 7,
+            Reason::SignatureInvalid => 8,
+            Reason::CategoryMismatch => 9,
+        }
+    }
+}
+
+/// What an accepted proof establishes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifiedClaim {
+    pub image_id: Digest,
+    /// Registered version of the guest, `None` for the compiled-in one
+    pub guest_version: Option<String>,
+    /// Config hash of the game played
+    pub game: [u8; 32],
+    /// Game ID from the canonical config encoding, `None` for guest 0.1.0;
+    /// a joined proof has the one its chunks committed
+    pub game_id: Option<[u8; 32]>,
+    /// Game name from the policy, if it lists games
+    pub game_name: Option<String>,
+    /// `None` for an anonymous proof whose credential is not revealed
+    pub user_cred: Option<String>,
+    pub scores: Vec<i64>,
+    /// Challenge the proof is bound to
+    pub challenge: Option<String>,
+    /// Salted route hash, see `host check-route`
+    pub route_commitment: Option<[u8; 32]>,
+    /// Identifies the route without revealing it, see `Output::route_nullifier`
+    pub route_nullifier: Option<[u8; 32]>,
+    /// X25519 key of the judge who can decrypt the route (`host judge-decrypt`)
+    pub judge_key: Option<[u8; 32]>,
+    /// Set instead of `scores` when the proof hides them
+    pub score_threshold: Option<ScoreThreshold>,
+    /// Facts about the final state the prover made public
+    pub disclosed: Vec<Disclosure>,
+    /// Category whose rules the route follows
+    pub category: Option<Category>,
+    /// Route steps the prover made public
+    pub route_steps: Vec<RouteStep>,
+}
+
+/// Outcome of verifying a proof against a policy
+pub struct Verdict {
+    /// `None` when the proof passed
+    pub reason: Option<Reason>,
+    pub message: String,
+    pub details: Value,
+    /// Set when the proof passed
+    pub claim: Option<VerifiedClaim>,
+}
+
+impl Verdict {
+    fn reject(reason: Reason, message: impl Into<String>, details: Value) -> Self {
+        Self { reason: Some(reason), message: message.into(), details, claim: None }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        self.reason.map_or(0, Reason::exit_code)
+    }
+
+    /// Machine-readable verdict, extending the plain `verify` output
+    pub fn to_json(&self) -> Value {
+        let mut result = json!({
+            "status": if self.reason.is_none() { "verified" } else { "rejected" },
+            "reason": self.reason.map_or("ok", Reason::as_str),
+        });
+        if self.reason.is_some() {
+            result["message"] = self.message.clone().into();
+        }
+        if let (Value::Object(result), Value::Object(details)) = (&mut result, &self.details) {
+            result.extend(details.clone());
+        }
+        result
+    }
+}
+
+/// Disclosed facts as a JSON object of name to value, flags as booleans
+pub fn disclosed_json(disclosed: &[Disclosure]) -> Value {
+    let facts = disclosed.iter().map(|d| {
+        let value = if d.fact.is_flag() { json!(d.value != 0) } else { json!(d.value) };
+        (d.fact.name(), value)
+    });
+    Value::Object(facts.collect())
+}
+
+fn parse_digest(hex_str: &str) -> Result<[u8; 32]> {
+    let bytes = hex::decode(hex_str).with_context(|| format!("Invalid hex '{}'", hex_str))?;
+    bytes.try_into().map_err(|_| anyhow::anyhow!("'{}' is not 32 bytes", hex_str))
+}
+
+impl Policy {
+    pub fn load(path: &str) -> Result<Self> {
+        let text = fs::read_to_string(path).with_context(|| format!("Failed to read policy {}", path))?;
+        toml::from_str(&text).with_context(|| format!("Invalid policy {}", path))
+    }
+
+    /// Parse `--policy <file>`, then `--game <hash>[=name]`, `--image-id <hex>`,
+    /// `--min-score <n>`, `--cred-pattern <regex>`, `--cred-min-len <n>`,
+    /// `--cred-max-len <n>`, `--require-signature`, `--challenge <text>` and
+    /// `--category <id|file.json>`
+    ///
+    /// Flags add to or override what the policy file says.
+    pub fn from_args(args: &[String]) -> Result<Self> {
+        let mut policy = match args.iter().position(|a| a == "--policy") {
+            Some(i) => Self::load(args.get(i + 1).context("--policy requires a value")?)?,
+            None => Self::default(),
+        };
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut value = || iter.next().with_context(|| format!("{} requires a value", arg));
+            match arg.as_str() {
+                "--policy" => {
+                    value()?;
+                }
+                "--game" => {
+                    let game = value()?;
+                    let (hash, name) = game.split_once('=').unwrap_or((game, ""));
+                    policy.games.insert(hash.to_string(), name.to_string());
+                }
+                "--image-id" => policy.image_ids.push(value()?.clone()),
+                "--min-score" => policy.min_score = Some(value()?.parse().context("Invalid min score")?),
+                "--cred-pattern" => policy.credential.pattern = Some(value()?.clone()),
+                "--cred-min-len" => {
+                    policy.credential.min_len = Some(value()?.parse().context("Invalid credential length")?);
+                }
+                "--cred-max-len" => {
+                    policy.credential.max_len = Some(value()?.parse().context("Invalid credential length")?);
+                }
+                "--require-signature" => policy.credential.require_signature = true,
+                "--challenge" => policy.challenge = Some(value()?.clone()),
+                "--category" => policy.category = Some(load_category(value()?)?),
+                _ => bail!("Unknown verify option '{}'", arg),
+            }
+        }
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    /// Catch policy typos before any proof is judged by them
+    fn validate(&self) -> Result<()> {
+        for hash in self.games.keys() {
+            parse_digest(hash).context("Bad game hash in policy")?;
+        }
+        for id in &self.image_ids {
+            parse_digest(id).context("Bad image ID in policy")?;
+        }
+        if let Some(pattern) = &self.credential.pattern {
+            Regex::new(pattern).context("Bad credential pattern in policy")?;
+        }
+        Ok(())
+    }
+
+    fn accepted_images(&self, current_image_id: Option<Digest>, registry: &Registry) -> Vec<Digest> {
+        if self.image_ids.is_empty() {
+            let mut ids: Vec<_> = current_image_id.into_iter().collect();
+            ids.extend(registry.image_ids());
+            return ids;
+        }
+        self.image_ids.iter().filter_map(|id| parse_digest(id).ok()).map(Digest::from).collect()
+    }
+
+    fn check_credential(&self, cred: &str) -> Option<String> {
+        let rules = &self.credential;
+        let cred = cred.strip_suffix('\n').unwrap_or(cred);
+        let len = cred.chars().count();
+        if rules.min_len.is_some_and(|min| len < min) || rules.max_len.is_some_and(|max| len > max) {
+            return Some(format!("Credential length {} out of range", len));
+        }
+        if let Some(pattern) = &rules.pattern {
+            let re = Regex::new(&format!("^(?:{})$", pattern)).ok()?;
+            if !re.is_match(cred) {
+                return Some(format!("Credential does not match '{}'", pattern));
+            }
+        }
+        None
+    }
+
+    /// Why a proof committing `category` is not for the policy's category
+    fn check_category(&self, category: Option<&Category>) -> Option<String> {
+        let expected = self.category.as_ref()?;
+        let id = &expected.id;
+        match category {
+            None => Some(format!("Proof is not for category '{}'", id)),
+            Some(category) if category.id != *id => Some(format!("Proof is for category '{}', not '{}'", category.id, id)),
+            // The same ID with weaker rules is not that category
+            Some(category) if category != expected => Some(format!("Proof uses other rules than category '{}'", id)),
+            Some(_) => None,
+        }
+    }
+
+    /// Check a shipped credential (and the salt of its reveal) against the
+    /// journal commitment, then its identity signature, if it is a key
+    fn check_committed_credential(
+        &self,
+        proof: &ProofEnvelope,
+        cred: &[u8],
+        output: &xixi_core::Output,
+        details: &mut Value,
+    ) -> std::result::Result<String, (Reason, String)> {
+        // Its plain hash could be the salted commitment of another credential
+        if proof.cred_salt.is_empty() && cred.starts_with(SALTED_CRED_DOMAIN) {
+            return Err((Reason::CredentialRejected, "Credential looks like a salted commitment".into()));
+        }
+        if credential_commitment(cred, &proof.cred_salt) != output.user_cred_hash {
+            return Err((Reason::InvalidProof, "User credential hash mismatch".into()));
+        }
+        // A key credential must have signed the journal
+        match Identity::from_credential(cred) {
+            Ok(Some(identity)) => {
+                details["identity"] = identity.to_json();
+                if let Err(e) = identity.verify(&journal_digest(&proof.receipt), &proof.signature) {
+                    return Err((Reason::SignatureInvalid, format!("{:#}", e)));
+                }
+            }
+            Ok(None) if self.credential.require_signature => {
+                return Err((Reason::SignatureInvalid, "Credential is not a public-key identity".into()));
+            }
+            Ok(None) => {}
+            Err(e) => return Err((Reason::CredentialRejected, format!("{:#}", e))),
+        }
+        String::from_utf8(cred.to_vec())
+            .map_err(|e| (Reason::CredentialRejected, format!("Invalid UTF-8 in user credential: {}", e)))
+    }
+
+    /// Check the credential shipped with `proof` against its journal, and
+    /// the identity signature, as [`Policy::verify`] does; for proofs that
+    /// are used without a verdict, such as the entries of a leaderboard
+    pub fn check_shipped_credential(&self, proof: &ProofEnvelope) -> std::result::Result<(), (Reason, String)> {
+        let output = proof.output().map_err(|e| (Reason::InvalidProof, format!("Bad journal: {:#}", e)))?;
+        let cred = proof
+            .user_cred
+            .as_deref()
+            .ok_or((Reason::CredentialRejected, "Credential is withheld (anonymous proof)".to_string()))?;
+        self.check_committed_credential(proof, cred, &output, &mut json!({})).map(|_| ())
+    }
+
+    /// Check a proof against this policy; `current_image_id` and the
+    /// registry are accepted when the policy lists no image IDs
+    pub fn verify(&self, proof: &ProofEnvelope, current_image_id: Option<Digest>, registry: &Registry) -> Verdict {
+        let accepted = self.accepted_images(current_image_id, registry);
+        let mut details = json!({});
+
+        // 1. Receipt must verify against one of the accepted images
+        if let Some(image_id) = proof.image_id {
+            details["image_id"] = image_id.to_string().into();
+            if !accepted.contains(&image_id) {
+                return Verdict::reject(Reason::ImageNotAllowed, format!("Guest image {} is not accepted", image_id), details);
+            }
+        }
+        let mut error = None;
+        let image_id = accepted.iter().find(|id| match proof.receipt.verify(**id) {
+            Ok(()) => true,
+            Err(e) => {
+                error = Some(e);
+                false
+            }
+        });
+        let Some(image_id) = image_id else {
+            let message = format!("Receipt verification failed: {}", error.map(|e| e.to_string()).unwrap_or_default());
+            return Verdict::reject(Reason::InvalidProof, message, details);
+        };
+        let guest_version = registry.find_id(image_id).map(|entry| entry.version.clone());
+        details["image_id"] = image_id.to_string().into();
+        details["guest_version"] = match &guest_version {
+            Some(version) => version.clone().into(),
+            None if Some(*image_id) == current_image_id => "current".into(),
+            None => Value::Null,
+        };
+
+        // 2. Journal must match the credential shipped with it
+        let output: xixi_core::Output = match proof.output() {
+            Ok(output) => output,
+            Err(e) => return Verdict::reject(Reason::InvalidProof, format!("Bad journal: {:#}", e), details),
+        };
+        if output.chunk_image_id.is_some_and(|id| Digest::from(id) != *image_id) {
+            return Verdict::reject(Reason::InvalidProof, "Joined from chunks proven with another guest image", details);
+        }
+        // The file must carry the route ciphertext the journal attests
+        if let Some(ciphertext) = &output.route_ciphertext {
+            details["judge_key"] = hex::encode(ciphertext.judge_key).into();
+            if Sha256::digest(&proof.route_ciphertext)[..] != ciphertext.ciphertext_hash {
+                return Verdict::reject(Reason::InvalidProof, "Route ciphertext missing or altered", details);
+            }
+        }
+        let user_cred = match &proof.user_cred {
+            Some(cred) => match self.check_committed_credential(proof, cred, &output, &mut details) {
+                Ok(cred) => Some(cred),
+                Err((reason, message)) => return Verdict::reject(reason, message, details),
+            },
+            // Anonymous: nothing to hold the credential rules against
+            None if self.credential.restricts() => {
+                return Verdict::reject(Reason::CredentialRejected, "Credential is withheld (anonymous proof)", details)
+            }
+            None => None,
+        };
+        let game = hex::encode(output.config_hash);
+        details["game"] = game.clone().into();
+        let game_id = output.game_id.map(hex::encode);
+        if let Some(game_id) = &game_id {
+            details["game_id"] = game_id.clone().into();
+        }
+        details["usercred"] = user_cred.clone().into();
+        details["user_cred_hash"] = hex::encode(output.user_cred_hash).into();
+        match &output.score_threshold {
+            Some(threshold) => details["score_threshold"] = json!(threshold),
+            None => details["scores"] = output.scores.clone().into(),
+        }
+        details["challenge"] = output.challenge.clone().into();
+        if let Some(commitment) = output.route_commitment {
+            details["route_commitment"] = hex::encode(commitment).into();
+        }
+        if let Some(nullifier) = output.route_nullifier {
+            details["route_nullifier"] = hex::encode(nullifier).into();
+        }
+        if !output.disclosed.is_empty() {
+            details["disclosed"] = disclosed_json(&output.disclosed);
+        }
+        if let Some(category) = &output.category {
+            details["category"] = json!(category);
+        }
+        if !output.route_steps.is_empty() {
+            details["route_steps"] = json!(output.route_steps);
+        }
+
+        // 3. Policy rules
+        let mut game_name = None;
+        if !self.games.is_empty() {
+            // Game IDs stay valid when the rkyv layout, and so the config hash, changes
+            let accepted = |hash: &String| {
+                hash.eq_ignore_ascii_case(&game) || game_id.as_ref().is_some_and(|id| hash.eq_ignore_ascii_case(id))
+            };
+            let name = self.games.iter().find(|(hash, _)| accepted(hash)).map(|(_, name)| name);
+            match name {
+                Some(name) => {
+                    details["game_name"] = name.clone().into();
+                    game_name = Some(name.clone());
+                }
+                None => return Verdict::reject(Reason::GameNotAllowed, format!("Game {} is not accepted", game), details),
+            }
+        }
+        if let (Some(min_score), Some(threshold)) = (self.min_score, &output.score_threshold) {
+            // Only "at least the threshold" is known
+            let message = match threshold.reached.first() {
+                Some(true) if threshold.threshold >= min_score => None,
+                Some(true) => Some(format!("Proof only shows a score of at least {}, below {}", threshold.threshold, min_score)),
+                _ => Some(format!("Score is below the threshold {}", threshold.threshold)),
+            };
+            if let Some(message) = message {
+                return Verdict::reject(Reason::ScoreTooLow, message, details);
+            }
+        } else if let Some(min_score) = self.min_score {
+            match output.scores.first() {
+                Some(&score) if score >= min_score => {}
+                Some(score) => {
+                    return Verdict::reject(Reason::ScoreTooLow, format!("Score {} is below {}", score, min_score), details)
+                }
+                None => return Verdict::reject(Reason::ScoreTooLow, "Proof has no score", details),
+            }
+        }
+        if let Some(message) = user_cred.as_deref().and_then(|cred| self.check_credential(cred)) {
+            return Verdict::reject(Reason::CredentialRejected, message, details);
+        }
+        if let Some(challenge) = &self.challenge {
+            if output.challenge.as_ref() != Some(challenge) {
+                let message = match &output.challenge {
+                    Some(other) => format!("Proof is bound to challenge '{}', not '{}'", other, challenge),
+                    None => format!("Proof is not bound to challenge '{}'", challenge),
+                };
+                return Verdict::reject(Reason::ChallengeMismatch, message, details);
+            }
+        }
+        if let Some(message) = self.check_category(output.category.as_ref()) {
+            return Verdict::reject(Reason::CategoryMismatch, message, details);
+        }
+
+        let claim = VerifiedClaim {
+            image_id: *image_id,
+            guest_version,
+            game: output.config_hash,
+            game_id: output.game_id,
+            game_name,
+            user_cred,
+            scores: output.scores,
+            challenge: output.challenge,
+            route_commitment: output.route_commitment,
+            route_nullifier: output.route_nullifier,
+            judge_key: output.route_ciphertext.map(|c| c.judge_key),
+            score_threshold: output.score_threshold,
+            disclosed: output.disclosed,
+            category: output.category,
+            route_steps: output.route_steps,
+        };
+        Verdict { reason: None, message: "ok".to_string(), details, claim: Some(claim) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn no_shop(forbidden_nodes: Vec<u32>) -> Category {
+        Category { id: "no-shop".into(), forbidden_nodes, ..Category::default() }
+    }
+
+    #[test]
+    fn category_pins_rules() {
+        let policy = Policy::from_args(&args(&["--category", "100%"])).unwrap();
+        assert_eq!(policy.check_category(Category::builtin("100%").as_ref()), None);
+        let weaker = Category { id: "100%".into(), ..Category::default() };
+        assert!(policy.check_category(Some(&weaker)).is_some());
+        assert!(policy.check_category(Category::builtin("any%").as_ref()).is_some());
+        assert!(policy.check_category(None).is_some());
+
+        let policy: Policy = toml::from_str("[category]\nid = \"no-shop\"\nforbidden_nodes = [17, 18]").unwrap();
+        assert_eq!(policy.check_category(Some(&no_shop(vec![17, 18]))), None);
+        assert!(policy.check_category(Some(&no_shop(vec![17]))).is_some());
+    }
+
+    #[test]
+    fn category_from_file() {
+        let path = std::env::temp_dir().join(format!("xixi-zk-test-category-{}.json", std::process::id()));
+        fs::write(&path, r#"{"id": "no-shop", "forbidden_nodes": [17, 18]}"#).unwrap();
+        let path = path.to_str().unwrap();
+
+        let flag = Policy::from_args(&args(&["--category", path])).unwrap();
+        let file: Policy = toml::from_str(&format!("category = {:?}", path)).unwrap();
+        assert_eq!(flag.category, Some(no_shop(vec![17, 18])));
+        assert_eq!(file.category, flag.category);
+        fs::remove_file(path).unwrap();
+
+        // A custom ID alone says nothing about its rules
+        assert!(Policy::from_args(&args(&["--category", "no-shop"])).is_err());
+        assert!(toml::from_str::<Policy>("category = \"no-shop\"").is_err());
+    }
+
+    const GAME: &str = "5cc8681fb14dd7a453b3cc0d673310cdc22f3039c5e87f1b70998448d75aaa30";
+    const IMAGE: &str = "5be9bd21c4e75c2b4ccef0e7b6308f5ad5352940b3ccd83aaf22a2a229f67b61";
+
+    #[test]
+    fn file_then_flags() {
+        let path = std::env::temp_dir().join(format!("xixi-zk-test-policy-{}.toml", std::process::id()));
+        let text = format!(
+            "image_ids = [\"{}\"]\nmin_score = 40000\nchallenge = \"cup\"\n\n[games]\n{} = \"xixi\"\n\n[credential]\npattern = \"user[0-9]+\"\n",
+            IMAGE, GAME
+        );
+        fs::write(&path, text).unwrap();
+        let path = path.to_str().unwrap();
+        let other = "11".repeat(32);
+
+        // Flags before or after --policy override the file
+        let policy = Policy::from_args(&args(&["--min-score", "50000", "--policy", path, "--game", &other, "--require-signature"]));
+        fs::remove_file(path).unwrap();
+        let policy = policy.unwrap();
+        assert_eq!(policy.image_ids, vec![IMAGE.to_string()]);
+        assert_eq!((policy.min_score, policy.challenge.as_deref()), (Some(50000), Some("cup")));
+        assert_eq!(policy.games.get(GAME).map(String::as_str), Some("xixi"));
+        assert_eq!(policy.games.get(&other).map(String::as_str), Some(""));
+        assert_eq!(policy.credential.pattern.as_deref(), Some("user[0-9]+"));
+        assert!(policy.credential.require_signature);
+    }
+
+    #[test]
+    fn rejects_typos() {
+        for bad in [
+            &["--min-scor", "1"][..],
+            &["--min-score"],
+            &["--min-score", "many"],
+            &["--image-id", "5be9bd"],
+            &["--game", "00=xixi"],
+            &["--cred-pattern", "user["],
+            &["--policy", "/nonexistent/policy.toml"],
+        ] {
+            assert!(Policy::from_args(&args(bad)).is_err(), "{:?}", bad);
+        }
+        assert!(toml::from_str::<Policy>("min_scores = 1").is_err());
+        assert!(toml::from_str::<Policy>("[credential]\nmax_length = 1").is_err());
+    }
+
+    #[test]
+    fn credential_rules() {
+        let policy = Policy::from_args(&args(&["--cred-pattern", "user[0-9]+", "--cred-min-len", "5", "--cred-max-len", "8"])).unwrap();
+        // The pattern must match the whole credential, newline aside
+        assert_eq!(policy.check_credential("user42\n"), None);
+        assert!(policy.check_credential("user").is_some());
+        assert!(policy.check_credential("user123456").is_some());
+        assert!(policy.check_credential("xuser42").is_some());
+        assert!(policy.check_credential("user42x").is_some());
+        assert!(!Policy::default().credential.restricts());
+    }
+
+    #[test]
+    fn exit_codes() {
+        let reasons = [
+            Reason::InvalidProof,
+            Reason::ImageNotAllowed,
+            Reason::GameNotAllowed,
+            Reason::ScoreTooLow,
+            Reason::CredentialRejected,
+            Reason::ChallengeMismatch,
+            Reason::SignatureInvalid,
+            Reason::CategoryMismatch,
+        ];
+        let codes: Vec<_> = reasons.iter().map(|r| r.exit_code()).collect();
+        assert_eq!(codes, (2..=9).collect::<Vec<_>>());
+        let names: std::collections::BTreeSet<_> = reasons.iter().map(|r| r.as_str()).collect();
+        assert_eq!(names.len(), reasons.len());
+
+        let verdict = Verdict::reject(Reason::ScoreTooLow, "Score 1 is below 2", json!({"scores": [1]}));
+        assert_eq!(verdict.exit_code(), 5);
+        assert_eq!(
+            verdict.to_json(),
+            json!({"status": "rejected", "reason": "score_too_low", "message": "Score 1 is below 2", "scores": [1]})
+        );
+        let passed = Verdict { reason: None, message: "ok".into(), details: json!({}), claim: None };
+        assert_eq!((passed.exit_code(), passed.to_json()), (0, json!({"status": "verified", "reason": "ok"})));
+    }
+
+    #[test]
+    fn image_not_allowed() {
+        use risc0_zkvm::{FakeReceipt, InnerReceipt, Receipt, ReceiptClaim};
+
+        let claim = ReceiptClaim::ok(Digest::ZERO, Vec::new());
+        let receipt = Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), Vec::new());
+        let proof = ProofEnvelope::new(Digest::ZERO, receipt, None);
+        let policy = Policy::from_args(&args(&["--image-id", IMAGE])).unwrap();
+        let registry = Registry::load().unwrap();
+
+        // Judged by the header before the receipt is looked at
+        let verdict = policy.verify(&proof, None, &registry);
+        assert_eq!((verdict.reason, verdict.exit_code()), (Some(Reason::ImageNotAllowed), 3));
+        assert!(verdict.claim.is_none());
+    }
+
+    #[test]
+    fn resplit_credential_is_rejected() {
+        use risc0_zkvm::{FakeReceipt, InnerReceipt, Receipt, ReceiptClaim};
+
+        let output = |cred_hash| xixi_core::Output {
+            version: xixi_core::OUTPUT_VERSION,
+            config_hash: [7; 32],
+            user_cred_hash: cred_hash,
+            scores: vec![1],
+            chunk_image_id: None,
+            challenge: None,
+            route_commitment: None,
+            route_nullifier: None,
+            route_ciphertext: None,
+            score_threshold: None,
+            disclosed: Vec::new(),
+            category: None,
+            route_steps: Vec::new(),
+            game_id: None,
+        };
+        let claim = ReceiptClaim::ok(Digest::ZERO, Vec::new());
+        let receipt = Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), Vec::new());
+        let mut proof = ProofEnvelope::new(Digest::ZERO, receipt, None);
+        let policy = Policy::default();
+        let check = |proof: &ProofEnvelope, cred: &[u8], output| {
+            policy.check_committed_credential(proof, cred, &output, &mut json!({})).map_err(|(reason, _)| reason)
+        };
+
+        let plain = output(credential_commitment(b"user3266", b""));
+        assert_eq!(check(&proof, b"user3266", plain), Ok("user3266".to_string()));
+        proof.cred_salt = b"6".to_vec();
+        assert_eq!(check(&proof, b"user326", output(credential_commitment(b"user3266", b""))), Err(Reason::InvalidProof));
+
+        // A key split into a plain credential no longer matches either
+        let key = b"ed25519:d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+        proof.cred_salt = key[40..].to_vec();
+        let signed = output(credential_commitment(key, b""));
+        assert_eq!(check(&proof, &key[..40], signed), Err(Reason::InvalidProof));
+
+        // A plain credential shaped like a salted preimage
+        proof.cred_salt.clear();
+        let mut forged = SALTED_CRED_DOMAIN.to_vec();
+        forged.extend_from_slice(&8u32.to_le_bytes());
+        forged.extend_from_slice(b"user3266salt");
+        let salted = output(credential_commitment(b"user3266", b"salt"));
+        assert_eq!(check(&proof, &forged, salted), Err(Reason::CredentialRejected));
+    }
+}
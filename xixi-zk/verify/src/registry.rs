@@ -0,0 +1,224 @@
+//! Registry of released guest images
+//!
+//! Every guest change (code, risc0 or Rust update) produces a new image ID,
+//! and receipts only verify against the image they were proven with. The
+//! registry in `xixi-zk-image/registry.toml` lists released images so
+//! proofs made with older guests keep verifying.
+//!
+//! The registry is compiled in; `XIXI_REGISTRY=<file>` reads another one.
+//! So is `xixi-zk-image/current_image_id`, the image ID of the guest in this
+//! source tree, which `host image-id --update` writes after a docker build.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use hex::FromHex;
+use risc0_zkvm::{compute_image_id, sha::Digest};
+use serde::{Deserialize, Serialize};
+
+const BUILTIN: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/../../xixi-zk-image/registry.toml"));
+const CURRENT: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/../../xixi-zk-image/current_image_id"));
+/// Checked-in `xixi-zk-image` directory
+pub const IMAGE_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../xixi-zk-image");
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ImageEntry {
+    pub version: String,
+    pub image_id: String,
+    /// Archived ELF, relative to the registry file
+    pub elf: Option<PathBuf>,
+    pub released: Option<String>,
+    pub risc0: Option<String>,
+    pub rust: Option<String>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Registry {
+    #[serde(default, rename = "image")]
+    pub images: Vec<ImageEntry>,
+    #[serde(skip)]
+    base_dir: PathBuf,
+}
+
+impl Registry {
+    fn parse(text: &str, base_dir: &Path) -> Result<Self> {
+        let mut registry: Self = toml::from_str(text)?;
+        registry.base_dir = base_dir.to_path_buf();
+        for entry in &registry.images {
+            entry.digest().with_context(|| format!("Bad image ID for guest {}", entry.version))?;
+        }
+        Ok(registry)
+    }
+
+    /// Registry file `host image-id --register` adds to: `XIXI_REGISTRY`, or
+    /// the checked-in one the next build compiles in
+    pub fn path() -> PathBuf {
+        match std::env::var_os("XIXI_REGISTRY") {
+            Some(path) => PathBuf::from(path),
+            None => Path::new(IMAGE_DIR).join("registry.toml"),
+        }
+    }
+
+    /// Append `entry` to the registry file at `path`, keeping its comments
+    pub fn append(path: &Path, entry: ImageEntry) -> Result<()> {
+        let mut text = fs::read_to_string(path).with_context(|| format!("Failed to read registry {}", path.display()))?;
+        let registry = Self::parse(&text, Path::new("."))?;
+        let image_id = entry.digest().with_context(|| format!("Bad image ID for guest {}", entry.version))?;
+        if registry.find_version(&entry.version).is_ok() || registry.find_id(&image_id).is_some() {
+            bail!("Guest {} ({}) is already registered", entry.version, entry.image_id);
+        }
+        let table = Self { images: vec![entry], base_dir: PathBuf::new() };
+        text.push('\n');
+        text.push_str(&toml::to_string(&table)?);
+        fs::write(path, text).with_context(|| format!("Failed to write registry {}", path.display()))
+    }
+
+    /// The compiled-in registry, or the one named by `XIXI_REGISTRY`
+    pub fn load() -> Result<Self> {
+        match std::env::var_os("XIXI_REGISTRY") {
+            Some(path) => {
+                let path = PathBuf::from(path);
+                let text = fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read registry {}", path.display()))?;
+                let base_dir = path.parent().unwrap_or(Path::new("."));
+                Self::parse(&text, base_dir).with_context(|| format!("Invalid registry {}", path.display()))
+            }
+            None => Self::parse(BUILTIN, Path::new(IMAGE_DIR)).context("Invalid built-in registry"),
+        }
+    }
+
+    pub fn image_ids(&self) -> Vec<Digest> {
+        self.images.iter().filter_map(|entry| entry.digest().ok()).collect()
+    }
+
+    pub fn find_id(&self, image_id: &Digest) -> Option<&ImageEntry> {
+        self.images.iter().find(|entry| entry.digest().ok().as_ref() == Some(image_id))
+    }
+
+    pub fn find_version(&self, version: &str) -> Result<&ImageEntry> {
+        match self.images.iter().find(|entry| entry.version == version) {
+            Some(entry) => Ok(entry),
+            None => {
+                let known: Vec<_> = self.images.iter().map(|entry| entry.version.as_str()).collect();
+                bail!("Unknown guest version '{}', registered: {:?}", version, known)
+            }
+        }
+    }
+
+    /// Path of the archived ELF of `entry`, if it has one
+    pub fn elf_path(&self, entry: &ImageEntry) -> Option<PathBuf> {
+        entry.elf.as_ref().map(|elf| self.base_dir.join(elf))
+    }
+
+    /// Read the archived ELF of `entry`, checking it still has the registered ID
+    pub fn load_elf(&self, entry: &ImageEntry) -> Result<Vec<u8>> {
+        let Some(path) = self.elf_path(entry) else {
+            bail!("Guest {} has no archived ELF", entry.version);
+        };
+        let bytes = fs::read(&path).with_context(|| format!("Failed to read ELF {}", path.display()))?;
+        let actual = compute_image_id(&bytes)?;
+        if actual != entry.digest()? {
+            bail!("ELF {} has image ID {}, registry says {}", path.display(), actual, entry.image_id);
+        }
+        Ok(bytes)
+    }
+}
+
+/// Image ID of the guest in this source tree, `None` until it is generated
+pub fn current_image_id() -> Result<Option<Digest>> {
+    parse_image_id_file(CURRENT)
+}
+
+/// Contents of a `current_image_id` file: hex, or empty for none
+pub fn parse_image_id_file(text: &str) -> Result<Option<Digest>> {
+    match text.trim() {
+        "" => Ok(None),
+        hex => Ok(Some(Digest::from_hex(hex).context("Bad image ID in current_image_id")?)),
+    }
+}
+
+impl ImageEntry {
+    pub fn digest(&self) -> Result<Digest> {
+        Ok(Digest::from_hex(&self.image_id)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "5be9bd21c4e75c2b4ccef0e7b6308f5ad5352940b3ccd83aaf22a2a229f67b61";
+
+    fn entry(version: &str, image_id: &str) -> ImageEntry {
+        ImageEntry {
+            version: version.into(),
+            image_id: image_id.into(),
+            elf: Some(PathBuf::from(format!("xixi_verifier-{}.bin", version))),
+            released: None,
+            risc0: Some("2.3.1".into()),
+            rust: None,
+            notes: Some("test".into()),
+        }
+    }
+
+    #[test]
+    fn append_keeps_comments() {
+        let path = std::env::temp_dir().join(format!("xixi-zk-test-registry-{}.toml", std::process::id()));
+        fs::write(&path, BUILTIN).unwrap();
+        let other = "00".repeat(32);
+
+        Registry::append(&path, entry("0.2.0", &other)).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with(BUILTIN));
+        let registry = Registry::parse(&text, Path::new("/images")).unwrap();
+        let added = registry.find_version("0.2.0").unwrap();
+        assert_eq!(added.digest().unwrap(), Digest::from_hex(&other).unwrap());
+        assert_eq!(registry.elf_path(added), Some(PathBuf::from("/images/xixi_verifier-0.2.0.bin")));
+        assert!(registry.find_version("0.1.0").is_ok());
+
+        // Neither the version nor the image may be registered twice
+        assert!(Registry::append(&path, entry("0.2.0", ID)).is_err());
+        assert!(Registry::append(&path, entry("0.3.0", ID)).is_err());
+        assert!(Registry::append(&path, entry("0.3.0", "not hex")).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), text);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn image_id_file() {
+        assert_eq!(parse_image_id_file("").unwrap(), None);
+        assert_eq!(parse_image_id_file(&format!("{}\n", ID)).unwrap(), Some(Digest::from_hex(ID).unwrap()));
+        assert!(parse_image_id_file("5be9bd").is_err());
+    }
+
+    #[test]
+    fn archived_elf() {
+        let registry = Registry::load().unwrap();
+        let entry = registry.find_version("0.1.0").unwrap();
+        let elf = registry.load_elf(entry).unwrap();
+        assert_eq!(compute_image_id(&elf).unwrap(), Digest::from_hex(ID).unwrap());
+        assert_eq!(registry.find_id(&Digest::from_hex(ID).unwrap()).map(|e| e.version.as_str()), Some("0.1.0"));
+
+        let err = registry.find_version("9.9.9").unwrap_err().to_string();
+        assert!(err.contains("0.1.0"), "{}", err);
+    }
+
+    #[test]
+    fn refuses_swapped_elf() {
+        // Registered under another image ID than the ELF has
+        let text = format!("[[image]]\nversion = \"0.2.0\"\nimage_id = \"{}\"\nelf = \"xixi_verifier.bin\"\n", "00".repeat(32));
+        let registry = Registry::parse(&text, Path::new(IMAGE_DIR)).unwrap();
+        let entry = registry.find_version("0.2.0").unwrap();
+        assert!(registry.load_elf(entry).is_err());
+
+        let text = format!("[[image]]\nversion = \"0.2.0\"\nimage_id = \"{}\"\n", ID);
+        let registry = Registry::parse(&text, Path::new(IMAGE_DIR)).unwrap();
+        assert!(registry.load_elf(registry.find_version("0.2.0").unwrap()).is_err());
+
+        assert!(Registry::parse("[[image]]\nversion = \"0.2.0\"\nimage_id = \"5be9bd\"\n", Path::new(".")).is_err());
+        assert!(Registry::parse("[[image]]\nversion = \"0.2.0\"\nimage_idx = \"\"\n", Path::new(".")).is_err());
+    }
+}
@@ -0,0 +1,138 @@
+//! Deferred credential reveal
+//!
+//! An anonymous proof (`host prove --anonymous`) commits a salted hash of
+//! the credential, and the proof file leaves the credential out, so it only
+//! says "someone scored X". The reveal file holds the credential and salt;
+//! whoever gets it can check it against the commitment with `verify
+//! --reveal`. Without the salt a short credential could be found by hashing
+//! guesses. The salt is never in the proof file, only in the reveal.
+//!
+//! ```json
+//! {"user_cred_hash": "<hex>", "usercred": "user42\n", "salt": "<hex>"}
+//! ```
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::envelope::ProofEnvelope;
+
+/// Domain of salted credential commitments
+pub const SALTED_CRED_DOMAIN: &[u8] = b"xixi-zk cred";
+
+/// Credential hash the journal commits: SHA-256 of the credential, or with a
+/// salt SHA-256 of `SALTED_CRED_DOMAIN || len(cred) as u32 LE || cred || salt`
+///
+/// The length prefix keeps bytes from moving between credential and salt.
+pub fn credential_commitment(cred: &[u8], salt: &[u8]) -> [u8; 32] {
+    if salt.is_empty() {
+        return Sha256::digest(cred).into();
+    }
+    Sha256::new()
+        .chain_update(SALTED_CRED_DOMAIN)
+        .chain_update((cred.len() as u32).to_le_bytes())
+        .chain_update(cred)
+        .chain_update(salt)
+        .finalize()
+        .into()
+}
+
+/// Credential and salt behind an anonymous proof
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reveal {
+    /// Commitment in the journal (hex), to tell reveals apart
+    pub user_cred_hash: String,
+    pub usercred: String,
+    /// Salt (hex)
+    pub salt: String,
+}
+
+impl Reveal {
+    pub fn new(cred: &str, salt: &[u8]) -> Self {
+        Self {
+            user_cred_hash: hex::encode(credential_commitment(cred.as_bytes(), salt)),
+            usercred: cred.to_string(),
+            salt: hex::encode(salt),
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).with_context(|| format!("Failed to read reveal {}", path.display()))?;
+        serde_json::from_str(&text).with_context(|| format!("Invalid reveal {}", path.display()))
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)? + "\n")?;
+        Ok(())
+    }
+
+    /// Put the credential back into `proof` if it matches the commitment in
+    /// its journal
+    pub fn apply(&self, proof: &mut ProofEnvelope) -> Result<()> {
+        let salt = hex::decode(&self.salt).context("Invalid salt hex in reveal")?;
+        if salt.is_empty() {
+            bail!("Reveal has no salt");
+        }
+        let committed = proof.output().context("Reveals apply to player proofs only")?.user_cred_hash;
+        if credential_commitment(self.usercred.as_bytes(), &salt) != committed {
+            bail!("Reveal does not match the credential committed in the proof");
+        }
+        proof.user_cred = Some(self.usercred.as_bytes().to_vec());
+        proof.cred_salt = salt;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use risc0_zkvm::sha::Digest as ImageId;
+    use risc0_zkvm::{FakeReceipt, InnerReceipt, Receipt, ReceiptClaim};
+
+    use super::*;
+
+    #[test]
+    fn bytes_cannot_move_to_the_salt() {
+        let plain = credential_commitment(b"user3266", b"");
+        assert_ne!(credential_commitment(b"user326", b"6"), plain);
+        assert_ne!(credential_commitment(b"user32", b"66"), credential_commitment(b"user326", b"6"));
+        // Unsalted stays the plain hash older proofs commit
+        assert_eq!(plain, <[u8; 32]>::from(Sha256::digest(b"user3266")));
+    }
+
+    #[test]
+    fn resplit_reveal_is_rejected() {
+        let reveal = Reveal::new("user3266", b"66");
+        let words = risc0_zkvm::serde::to_vec(&xixi_core::Output {
+            version: xixi_core::OUTPUT_VERSION,
+            config_hash: [7; 32],
+            user_cred_hash: credential_commitment(b"user3266", b"66"),
+            scores: vec![1],
+            chunk_image_id: None,
+            challenge: None,
+            route_commitment: None,
+            route_nullifier: None,
+            route_ciphertext: None,
+            score_threshold: None,
+            disclosed: Vec::new(),
+            category: None,
+            route_steps: Vec::new(),
+            game_id: None,
+        })
+        .unwrap();
+        let journal: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+        let claim = ReceiptClaim::ok(ImageId::ZERO, journal.clone());
+        let receipt = Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), journal);
+        let mut proof = ProofEnvelope::new(ImageId::ZERO, receipt, None);
+
+        let resplit = Reveal { usercred: "user326".into(), salt: hex::encode(b"666"), ..reveal.clone() };
+        assert!(resplit.apply(&mut proof).is_err());
+        let unsalted = Reveal { usercred: "user3266".into(), salt: String::new(), ..reveal.clone() };
+        assert!(unsalted.apply(&mut proof).is_err());
+        reveal.apply(&mut proof).unwrap();
+        assert_eq!(proof.user_cred.as_deref(), Some(&b"user3266"[..]));
+    }
+}